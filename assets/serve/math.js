@@ -0,0 +1,288 @@
+// A tiny TeX to MathML converter to preview ConciseMark math without network access.
+//
+// Only a common subset of TeX is supported (scripts, fractions, roots, greek letters, common
+// operators and functions), unknown commands are displayed as is. Math is rendered by the
+// browser's native MathML support.
+(function () {
+    var NS = "http://www.w3.org/1998/Math/MathML";
+    var IDENTIFIERS = {
+        alpha: "α", beta: "β", gamma: "γ", delta: "δ", epsilon: "ϵ", varepsilon: "ε",
+        zeta: "ζ", eta: "η", theta: "θ", vartheta: "ϑ", iota: "ι", kappa: "κ", lambda: "λ",
+        mu: "μ", nu: "ν", xi: "ξ", pi: "π", rho: "ρ", sigma: "σ", tau: "τ", upsilon: "υ",
+        phi: "ϕ", varphi: "φ", chi: "χ", psi: "ψ", omega: "ω", Gamma: "Γ", Delta: "Δ",
+        Theta: "Θ", Lambda: "Λ", Xi: "Ξ", Pi: "Π", Sigma: "Σ", Phi: "Φ", Psi: "Ψ", Omega: "Ω",
+        infty: "∞", partial: "∂", nabla: "∇", ell: "ℓ", hbar: "ℏ", emptyset: "∅"
+    };
+    var OPERATORS = {
+        sum: "∑", prod: "∏", int: "∫", iint: "∬", oint: "∮", cdot: "⋅", times: "×",
+        div: "÷", pm: "±", mp: "∓", le: "≤", leq: "≤", ge: "≥", geq: "≥", ne: "≠", neq: "≠",
+        approx: "≈", equiv: "≡", sim: "∼", propto: "∝", to: "→", rightarrow: "→",
+        leftarrow: "←", Rightarrow: "⇒", Leftarrow: "⇐", leftrightarrow: "↔",
+        Leftrightarrow: "⇔", iff: "⟺", mapsto: "↦", in: "∈", notin: "∉", subset: "⊂",
+        subseteq: "⊆", supset: "⊃", supseteq: "⊇", cup: "∪", cap: "∩", forall: "∀",
+        exists: "∃", neg: "¬", land: "∧", lor: "∨", cdots: "⋯", ldots: "…", dots: "…",
+        circ: "∘", langle: "⟨", rangle: "⟩", lbrace: "{", rbrace: "}", "{": "{", "}": "}",
+        "|": "‖", mid: "∣", "%": "%", "$": "$", "&": "&", "#": "#", "_": "_"
+    };
+    var FUNCTIONS = [
+        "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh",
+        "cosh", "tanh", "log", "ln", "lg", "exp", "lim", "max", "min", "sup", "inf",
+        "det", "gcd", "deg", "dim", "arg"
+    ];
+    var SPACES = { ",": "0.1667em", ":": "0.2222em", ";": "0.2778em", " ": "0.25em", quad: "1em", qquad: "2em" };
+    var FONTS = { mathbf: "bold", mathit: "italic", mathrm: "normal", mathbb: "double-struck", mathcal: "script" };
+
+    function element(name, children, text) {
+        var node = document.createElementNS(NS, name);
+        if (text !== undefined) {
+            node.textContent = text;
+        }
+        (children || []).forEach(function (child) {
+            node.appendChild(child);
+        });
+        return node;
+    }
+
+    function row(nodes) {
+        return nodes.length === 1 ? nodes[0] : element("mrow", nodes);
+    }
+
+    function Parser(tex) {
+        this.tex = tex;
+        this.pos = 0;
+    }
+
+    Parser.prototype.skipSpaces = function () {
+        while (this.pos < this.tex.length && /\s/.test(this.tex[this.pos])) {
+            this.pos += 1;
+        }
+    };
+
+    // Parse atoms until `end` character or the end of input
+    Parser.prototype.parseExpression = function (end) {
+        var nodes = [];
+        for (;;) {
+            this.skipSpaces();
+            if (this.pos >= this.tex.length) {
+                break;
+            }
+            if (end && this.tex[this.pos] === end) {
+                this.pos += 1;
+                break;
+            }
+            if (this.tex.startsWith("\\right", this.pos) && end === "\\right") {
+                break;
+            }
+            var atom = this.parseScripts(this.parseAtom());
+            if (atom) {
+                nodes.push(atom);
+            }
+        }
+        return nodes;
+    };
+
+    Parser.prototype.parseArgument = function () {
+        this.skipSpaces();
+        if (this.tex[this.pos] === "{") {
+            this.pos += 1;
+            return row(this.parseExpression("}"));
+        }
+        return this.parseAtom() || element("mrow");
+    };
+
+    Parser.prototype.parseRawArgument = function () {
+        this.skipSpaces();
+        if (this.tex[this.pos] !== "{") {
+            return this.tex[this.pos++] || "";
+        }
+        var depth = 0;
+        var start = this.pos + 1;
+        for (; this.pos < this.tex.length; this.pos++) {
+            if (this.tex[this.pos] === "{") {
+                depth += 1;
+            } else if (this.tex[this.pos] === "}") {
+                depth -= 1;
+                if (depth === 0) {
+                    this.pos += 1;
+                    return this.tex.slice(start, this.pos - 1);
+                }
+            }
+        }
+        return this.tex.slice(start);
+    };
+
+    Parser.prototype.parseScripts = function (base) {
+        var sub = null;
+        var sup = null;
+        for (;;) {
+            this.skipSpaces();
+            var ch = this.tex[this.pos];
+            if (ch === "_" && !sub) {
+                this.pos += 1;
+                sub = this.parseArgument();
+            } else if (ch === "^" && !sup) {
+                this.pos += 1;
+                sup = this.parseArgument();
+            } else if (ch === "'" && !sup) {
+                this.pos += 1;
+                sup = element("mo", [], "′");
+            } else {
+                break;
+            }
+        }
+        base = base || element("mrow");
+        if (sub && sup) {
+            return element("msubsup", [base, sub, sup]);
+        } else if (sub) {
+            return element("msub", [base, sub]);
+        } else if (sup) {
+            return element("msup", [base, sup]);
+        }
+        return base;
+    };
+
+    Parser.prototype.parseAtom = function () {
+        var ch = this.tex[this.pos];
+        var match;
+        if (ch === "{") {
+            this.pos += 1;
+            return row(this.parseExpression("}"));
+        }
+        if (ch === "\\") {
+            return this.parseCommand();
+        }
+        if ((match = /^[0-9]+(\.[0-9]+)?/.exec(this.tex.slice(this.pos)))) {
+            this.pos += match[0].length;
+            return element("mn", [], match[0]);
+        }
+        this.pos += 1;
+        if (/[a-zA-Z]/.test(ch)) {
+            return element("mi", [], ch);
+        }
+        if (ch === "&" || ch === "}") {
+            return null;
+        }
+        return element("mo", [], ch);
+    };
+
+    Parser.prototype.parseCommand = function () {
+        var match = /^\\([a-zA-Z]+|.)/.exec(this.tex.slice(this.pos)) || ["\\", ""];
+        var name = match[1];
+        this.pos += match[0].length;
+        if (name === "frac" || name === "dfrac" || name === "tfrac") {
+            var numerator = this.parseArgument();
+            return element("mfrac", [numerator, this.parseArgument()]);
+        }
+        if (name === "sqrt") {
+            this.skipSpaces();
+            if (this.tex[this.pos] === "[") {
+                var end = this.tex.indexOf("]", this.pos);
+                var index = new Parser(this.tex.slice(this.pos + 1, end)).parseExpression();
+                this.pos = end + 1;
+                var radicand = this.parseArgument();
+                return element("mroot", [radicand, row(index)]);
+            }
+            return element("msqrt", [this.parseArgument()]);
+        }
+        if (name === "text" || name === "textrm" || name === "mbox" || name === "operatorname") {
+            return element(name === "operatorname" ? "mi" : "mtext", [], this.parseRawArgument());
+        }
+        if (FONTS[name]) {
+            var node = this.parseArgument();
+            if (node.tagName === "mi") {
+                node.setAttribute("mathvariant", FONTS[name]);
+                return node;
+            }
+            return element("mstyle", [node]);
+        }
+        if (name === "left" || name === "right" || name === "big" || name === "Big") {
+            this.skipSpaces();
+            var delimiter = this.parseAtom();
+            if (name === "left") {
+                var inner = this.parseExpression("\\right");
+                var nodes = [delimiter].concat(inner);
+                if (this.tex.startsWith("\\right", this.pos)) {
+                    this.pos += "\\right".length;
+                    this.skipSpaces();
+                    nodes.push(this.parseAtom());
+                }
+                return element("mrow", nodes.filter(function (node) {
+                    return node && node.textContent !== ".";
+                }));
+            }
+            return delimiter;
+        }
+        if (name === "begin" || name === "end") {
+            this.parseRawArgument();
+            return null;
+        }
+        if (name === "\\") {
+            return element("mspace", [], "");
+        }
+        if (SPACES[name]) {
+            var space = element("mspace");
+            space.setAttribute("width", SPACES[name]);
+            return space;
+        }
+        if (IDENTIFIERS[name]) {
+            return element("mi", [], IDENTIFIERS[name]);
+        }
+        if (OPERATORS[name]) {
+            return element("mo", [], OPERATORS[name]);
+        }
+        if (FUNCTIONS.indexOf(name) >= 0) {
+            var fn = element("mi", [], name);
+            fn.setAttribute("mathvariant", "normal");
+            return fn;
+        }
+        return element("mtext", [], "\\" + name);
+    };
+
+    function render(tex, display) {
+        var math = element("math", [row(new Parser(tex).parseExpression())]);
+        if (display) {
+            math.setAttribute("display", "block");
+        }
+        return math;
+    }
+
+    function renderTextNode(node) {
+        var pattern = /\$\$([\s\S]+?)\$\$|\$([^$]+?)\$/g;
+        var text = node.nodeValue;
+        var fragment = document.createDocumentFragment();
+        var last = 0;
+        var match;
+        while ((match = pattern.exec(text))) {
+            fragment.appendChild(document.createTextNode(text.slice(last, match.index)));
+            if (match[1] !== undefined) {
+                fragment.appendChild(render(match[1], true));
+            } else {
+                fragment.appendChild(render(match[2], false));
+            }
+            last = pattern.lastIndex;
+        }
+        if (last === 0) {
+            return;
+        }
+        fragment.appendChild(document.createTextNode(text.slice(last)));
+        node.parentNode.replaceChild(fragment, node);
+    }
+
+    function renderMath(root) {
+        var walker = document.createTreeWalker(root, NodeFilter.SHOW_TEXT, {
+            acceptNode: function (node) {
+                var parent = node.parentNode && node.parentNode.closest("code, pre, script, style, math");
+                return parent ? NodeFilter.FILTER_REJECT : NodeFilter.FILTER_ACCEPT;
+            }
+        });
+        var nodes = [];
+        while (walker.nextNode()) {
+            nodes.push(walker.currentNode);
+        }
+        nodes.forEach(renderTextNode);
+    }
+
+    document.addEventListener("DOMContentLoaded", function () {
+        renderMath(document.body);
+    });
+})();
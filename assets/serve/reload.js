@@ -0,0 +1,9 @@
+// Reload the page when the preview server tells us a source file is changed
+(function () {
+    var source = new EventSource("/__concisemark/events");
+    source.onmessage = function (event) {
+        if (event.data === "reload") {
+            window.location.reload();
+        }
+    };
+})();
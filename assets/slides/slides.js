@@ -0,0 +1,73 @@
+// Minimal slide navigation compatible with the reveal.js markup: one `section` of `.slides` is
+// shown at a time, arrow keys, space and page keys move between slides, `n` toggles speaker notes
+// and the current slide is kept in the url hash.
+(function () {
+    var slides = [];
+    var current = 0;
+    var progress;
+
+    function show(index) {
+        if (slides.length === 0) {
+            return;
+        }
+        current = Math.max(0, Math.min(index, slides.length - 1));
+        slides.forEach(function (slide, i) {
+            slide.classList.toggle("present", i === current);
+        });
+        progress.style.width = ((current + 1) / slides.length * 100) + "%";
+        if (location.hash !== "#" + (current + 1)) {
+            history.replaceState(null, "", "#" + (current + 1));
+        }
+    }
+
+    function fromHash() {
+        var index = parseInt(location.hash.slice(1), 10);
+        return isNaN(index) ? 0 : index - 1;
+    }
+
+    document.addEventListener("DOMContentLoaded", function () {
+        slides = Array.prototype.slice.call(document.querySelectorAll(".slides > section"));
+        progress = document.createElement("div");
+        progress.className = "progress";
+        document.body.appendChild(progress);
+        show(fromHash());
+    });
+
+    window.addEventListener("hashchange", function () {
+        show(fromHash());
+    });
+
+    document.addEventListener("keydown", function (event) {
+        switch (event.key) {
+            case "ArrowRight":
+            case "ArrowDown":
+            case "PageDown":
+            case " ":
+                show(current + 1);
+                break;
+            case "ArrowLeft":
+            case "ArrowUp":
+            case "PageUp":
+                show(current - 1);
+                break;
+            case "Home":
+                show(0);
+                break;
+            case "End":
+                show(slides.length - 1);
+                break;
+            case "n":
+                document.body.classList.toggle("show-notes");
+                break;
+            default:
+                return;
+        }
+        event.preventDefault();
+    });
+
+    document.addEventListener("click", function (event) {
+        if (!event.target.closest("a, aside")) {
+            show(current + (event.clientX < window.innerWidth / 3 ? -1 : 1));
+        }
+    });
+})();
@@ -0,0 +1,531 @@
+//! ConciseMark command line tool
+mod serve;
+mod watch;
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use concisemark::{
+    config::Config,
+    docx::Docx,
+    epub::Epub,
+    fmt::{self, FormatOptions},
+    site::Site,
+    Page, PageOptions,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "concisemark",
+    version,
+    about = "Render and inspect ConciseMark documents"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a file (or stdin if no file is given) to stdout or the output file
+    Render {
+        /// Input markdown file, read from stdin if it is not given or is `-`
+        input: Option<PathBuf>,
+        /// Output file, write to stdout if it is not given
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = Format::Html)]
+        format: Format,
+        /// Put HTML block elements on their own lines with indentation
+        #[arg(long)]
+        pretty: bool,
+    },
+    /// Dump the AST of a file (or stdin)
+    Ast {
+        /// Input markdown file, read from stdin if it is not given or is `-`
+        input: Option<PathBuf>,
+        /// Dump AST as json
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the meta of a file (or stdin) as json
+    Meta {
+        /// Input markdown file, read from stdin if it is not given or is `-`
+        input: Option<PathBuf>,
+    },
+    /// Render all markdown files in a directory into an output directory mirroring its structure
+    ///
+    /// Without source and output, the project is built by `concisemark.toml` found in the current
+    /// directory or its ancestors.
+    Build {
+        /// Source directory
+        #[arg(requires = "output")]
+        source: Option<PathBuf>,
+        /// Output directory
+        output: Option<PathBuf>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = Format::Html)]
+        format: Format,
+        /// Project config file, source and output given in command line take precedence
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Package markdown files into an EPUB e-book
+    ///
+    /// Each level-1 heading starts a chapter, book metadata is taken from the first file with a
+    /// meta.
+    Epub {
+        /// Input markdown files in reading order
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Output EPUB file
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Book title, taken from the meta if it is not given
+        #[arg(long)]
+        title: Option<String>,
+        /// Language of the book
+        #[arg(long, default_value = "en")]
+        language: String,
+        /// Make each file a chapter instead of splitting files by level-1 headings
+        #[arg(long)]
+        page_chapters: bool,
+    },
+    /// Convert markdown files into a Word document, each file starts on a new page
+    Docx {
+        /// Input markdown files
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Output DOCX file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Build a static site by `concisemark.toml`, pages are wrapped in the site template and
+    /// index pages are generated
+    Site {
+        /// Project config file, found in the current directory or its ancestors if not given
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Build a directory like `build` and rebuild changed files until interrupted
+    Watch {
+        /// Source directory
+        source: PathBuf,
+        /// Output directory
+        output: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = Format::Html)]
+        format: Format,
+        /// Polling interval in milliseconds
+        #[arg(short, long, default_value_t = 500)]
+        interval: u64,
+    },
+    /// Serve rendered pages of a directory over http with live reload
+    Serve {
+        /// Directory to serve
+        #[arg(default_value = ".")]
+        root: PathBuf,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 3000)]
+        port: u16,
+        /// Polling interval in milliseconds
+        #[arg(short, long, default_value_t = 500)]
+        interval: u64,
+    },
+    /// Format markdown files in place
+    Fmt {
+        /// Markdown files or directories to format
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Maximum line width, `0` means never wrap lines
+        #[arg(short, long, default_value_t = 80)]
+        width: usize,
+        /// Do not write files but report files which would be changed, exit with non-zero code if
+        /// there are any
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum Format {
+    Html,
+    /// Complete HTML page with head and styles
+    Document,
+    Latex,
+    Typst,
+    /// Man page, view it with `man -l`
+    Man,
+    /// LaTeX Beamer slides
+    Beamer,
+    /// Self-contained HTML slides
+    Slides,
+    Markdown,
+    Text,
+    /// Styled text for terminals
+    Ansi,
+}
+
+impl Format {
+    fn render(&self, page: &Page) -> String {
+        match self {
+            Format::Html => page.render(),
+            Format::Document => page.render_html_document(),
+            Format::Latex => page.render_latex(),
+            Format::Typst => page.render_typst(),
+            Format::Man => page.render_man(),
+            Format::Beamer => page.render_beamer(),
+            Format::Slides => page.render_slides(),
+            Format::Markdown => page.render_markdown(),
+            Format::Text => page.render_text(),
+            Format::Ansi => page.render_ansi(terminal_width()),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Html | Format::Document | Format::Slides => "html",
+            Format::Latex | Format::Beamer => "tex",
+            Format::Typst => "typ",
+            Format::Man => "1",
+            Format::Markdown => "md",
+            Format::Text | Format::Ansi => "txt",
+        }
+    }
+}
+
+/// Width of the terminal from `COLUMNS`, or 80 if it is unknown
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|columns| *columns > 0)
+        .unwrap_or(80)
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode> {
+    match cli.command {
+        Command::Render {
+            input,
+            output,
+            format,
+            pretty,
+        } => {
+            let options = PageOptions {
+                pretty_html: pretty,
+                ..Default::default()
+            };
+            let page = Page::new_with(read_input(input.as_deref())?, options);
+            let origin =
+                input.as_deref().map(|input| input.display().to_string());
+            for diagnostic in page.diagnostics() {
+                eprint!(
+                    "{}",
+                    diagnostic.pretty(&page.content, origin.as_deref())
+                );
+            }
+            write_output(output.as_deref(), &format.render(&page))?;
+        }
+        Command::Ast { input, json } => {
+            let page = Page::new(read_input(input.as_deref())?);
+            if json {
+                let value = page.ast.to_json(Some(&page.content));
+                println!("{}", serde_json::to_string_pretty(&value)?);
+            } else {
+                page.ast.dump(0, Some(&page.content));
+            }
+        }
+        Command::Meta { input } => {
+            let page = Page::new(read_input(input.as_deref())?);
+            println!("{}", serde_json::to_string_pretty(&page.meta)?);
+        }
+        Command::Build {
+            source,
+            output,
+            format,
+            config,
+        } => {
+            let mut config = if config.is_some() || source.is_none() {
+                load_config(config)?
+            } else {
+                Config::default()
+            };
+            if let (Some(source), Some(output)) = (source, output) {
+                config.source = source;
+                config.output = output;
+            }
+            for input in config.documents()? {
+                let relpath = input.strip_prefix(&config.source)?;
+                let options = config.options_for(relpath)?;
+                let (target, diagnostics) = render_file(
+                    &config.source,
+                    &config.output,
+                    &input,
+                    format,
+                    options,
+                )?;
+                println!("{} -> {}", input.display(), target.display());
+                for diagnostic in diagnostics {
+                    eprint!("{diagnostic}");
+                }
+            }
+        }
+        Command::Epub {
+            inputs,
+            output,
+            title,
+            language,
+            page_chapters,
+        } => {
+            let mut pages = vec![];
+            for input in &inputs {
+                let content = fs::read_to_string(input).with_context(|| {
+                    format!("failed to read {}", input.display())
+                })?;
+                pages.push(Page::new(content));
+            }
+            let mut epub = Epub::new("Untitled");
+            if let Some(meta) = pages.iter().find_map(|page| page.meta.as_ref())
+            {
+                epub = epub.with_meta(meta);
+            }
+            if let Some(title) = title {
+                epub.title = title;
+            }
+            let mut epub = epub
+                .with_language(language)
+                .with_page_chapters(page_chapters);
+            for (page, input) in pages.iter().zip(&inputs) {
+                epub.add(page, input.parent().unwrap_or(Path::new(".")));
+            }
+            let file = fs::File::create(&output).with_context(|| {
+                format!("failed to create {}", output.display())
+            })?;
+            epub.write(file)?;
+            println!("{}", output.display());
+        }
+        Command::Docx { inputs, output } => {
+            let mut docx: Option<Docx> = None;
+            for input in &inputs {
+                let content = fs::read_to_string(input).with_context(|| {
+                    format!("failed to read {}", input.display())
+                })?;
+                let page = Page::new(content);
+                let base = input.parent().unwrap_or(Path::new("."));
+                match docx.as_mut() {
+                    Some(docx) => docx.add(&page, base),
+                    None => docx = Some(Docx::from_page(&page, base)),
+                }
+            }
+            let file = fs::File::create(&output).with_context(|| {
+                format!("failed to create {}", output.display())
+            })?;
+            docx.unwrap_or_default().write(file)?;
+            println!("{}", output.display());
+        }
+        Command::Site { config } => {
+            let site = Site::new(load_config(config)?)?;
+            for path in site.build()? {
+                println!("{}", path.display());
+            }
+        }
+        Command::Watch {
+            source,
+            output,
+            format,
+            interval,
+        } => {
+            watch::run(
+                source,
+                output,
+                format,
+                Duration::from_millis(interval),
+            )?;
+        }
+        Command::Serve {
+            root,
+            host,
+            port,
+            interval,
+        } => {
+            serve::run(root, host, port, Duration::from_millis(interval))?;
+        }
+        Command::Fmt {
+            inputs,
+            width,
+            check,
+        } => {
+            let options = FormatOptions {
+                width: Some(width).filter(|&width| width > 0),
+            };
+            let mut unformatted = 0;
+            for input in inputs {
+                let files = if input.is_dir() {
+                    markdown_files(&input)?
+                } else {
+                    vec![input]
+                };
+                for file in files {
+                    let content =
+                        fs::read_to_string(&file).with_context(|| {
+                            format!("failed to read {}", file.display())
+                        })?;
+                    let formatted = fmt::format(&content, &options);
+                    if formatted == content {
+                        continue;
+                    }
+                    unformatted += 1;
+                    if check {
+                        println!("{}", file.display());
+                    } else {
+                        fs::write(&file, formatted)?;
+                    }
+                }
+            }
+            if check && unformatted > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Load config file `path`, or find it in the current directory and its ancestors
+fn load_config(path: Option<PathBuf>) -> Result<Config> {
+    let path = match path {
+        Some(path) => path,
+        None => Config::find(std::env::current_dir()?)
+            .with_context(|| format!("{} is not found", Config::FILE_NAME))?,
+    };
+    Config::load(path)
+}
+
+/// Page options of markdown file `doc` from the config file found in its directory or its
+/// ancestors, the default options are used if there is no config file
+fn options_for_file(doc: &Path) -> Result<PageOptions> {
+    let doc = doc
+        .canonicalize()
+        .with_context(|| format!("failed to read {}", doc.display()))?;
+    let Some(path) = Config::find(doc.parent().unwrap_or(Path::new(""))) else {
+        return Ok(PageOptions::default());
+    };
+    let config = Config::load(path)?;
+    let relpath = doc.strip_prefix(&config.source).unwrap_or(Path::new(""));
+    config.options_for(relpath)
+}
+
+fn read_input(input: Option<&Path>) -> Result<String> {
+    match input {
+        Some(path) if path != Path::new("-") => fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display())),
+        _ => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            Ok(content)
+        }
+    }
+}
+
+fn write_output(output: Option<&Path>, content: &str) -> Result<()> {
+    match output {
+        Some(path) => fs::write(path, content)
+            .with_context(|| format!("failed to write {}", path.display())),
+        None => Ok(io::stdout().write_all(content.as_bytes())?),
+    }
+}
+
+/// Collect all markdown files in `dir` recursively in a stable order
+fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    let mut files = vec![];
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            files.extend(markdown_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Render markdown file `input` in `source` directory into `output` directory, returns the
+/// rendered file path and pretty printed diagnostics of the file
+fn render_file(
+    source: &Path,
+    output: &Path,
+    input: &Path,
+    format: Format,
+    options: PageOptions,
+) -> Result<(PathBuf, Vec<String>)> {
+    let target = output_path(source, output, input, format)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = fs::read_to_string(input)
+        .with_context(|| format!("failed to read {}", input.display()))?;
+    let page = Page::new_with(content, options);
+    fs::write(&target, format.render(&page))
+        .with_context(|| format!("failed to write {}", target.display()))?;
+    let origin = input.display().to_string();
+    let diagnostics = page
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| diagnostic.pretty(&page.content, Some(&origin)))
+        .collect();
+    Ok((target, diagnostics))
+}
+
+/// Map `input` in `source` directory to its rendered file path in `output` directory
+fn output_path(
+    source: &Path,
+    output: &Path,
+    input: &Path,
+    format: Format,
+) -> Result<PathBuf> {
+    let relpath = input.strip_prefix(source)?;
+    Ok(output.join(relpath).with_extension(format.extension()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path() {
+        let path = output_path(
+            Path::new("docs"),
+            Path::new("public"),
+            Path::new("docs/guide/intro.md"),
+            Format::Latex,
+        )
+        .unwrap();
+        assert_eq!(path, Path::new("public/guide/intro.tex"));
+    }
+}
@@ -0,0 +1,445 @@
+//! Local preview server with live reload
+//!
+//! Markdown files are rendered on every request, static files are served as is, and browsers
+//! are notified to reload pages over server-sent events when any file in the served directory
+//! is changed. Nothing is loaded from network, math is rendered by the bundled script.
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use concisemark::{utils, Page};
+
+use crate::{
+    options_for_file,
+    watch::{fingerprint, Fingerprint},
+};
+
+const ASSETS_PATH: &str = "/__concisemark/";
+const EVENTS_PATH: &str = "/__concisemark/events";
+const ASSETS: &[(&str, &str, &str)] = &[
+    (
+        "style.css",
+        "text/css",
+        include_str!("../../../assets/serve/style.css"),
+    ),
+    (
+        "math.js",
+        "text/javascript",
+        include_str!("../../../assets/serve/math.js"),
+    ),
+    (
+        "reload.js",
+        "text/javascript",
+        include_str!("../../../assets/serve/reload.js"),
+    ),
+];
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new<B: Into<Vec<u8>>>(
+        status: &'static str,
+        content_type: &'static str,
+        body: B,
+    ) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn html<S: AsRef<str>>(status: &'static str, title: &str, body: S) -> Self {
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{}</title>
+<link rel="stylesheet" href="{ASSETS_PATH}style.css">
+<script defer src="{ASSETS_PATH}math.js"></script>
+<script defer src="{ASSETS_PATH}reload.js"></script>
+</head>
+<body>
+{}
+</body>
+</html>
+"#,
+            utils::escape_to_html(title),
+            body.as_ref()
+        );
+        Self::new(status, "text/html; charset=utf-8", html)
+    }
+
+    fn not_found() -> Self {
+        Self::html("404 Not Found", "Not Found", "<h1>Not Found</h1>")
+    }
+}
+
+/// Serve `root` at `host:port` until interrupted, files are checked for changes every `interval`
+pub fn run(
+    root: PathBuf,
+    host: String,
+    port: u16,
+    interval: Duration,
+) -> Result<()> {
+    let listener = TcpListener::bind((host.as_str(), port))?;
+    println!(
+        "serving {} at http://{} (press Ctrl-C to stop)",
+        root.display(),
+        listener.local_addr()?
+    );
+
+    let clients: Arc<Mutex<Vec<TcpStream>>> = Arc::default();
+    {
+        let root = root.clone();
+        let clients = clients.clone();
+        thread::spawn(move || {
+            let mut files = snapshot(&root);
+            loop {
+                thread::sleep(interval);
+                let current = snapshot(&root);
+                if current == files {
+                    continue;
+                }
+                files = current;
+                if let Ok(mut clients) = clients.lock() {
+                    // drop clients whose connection is closed
+                    clients.retain_mut(|client| {
+                        client.write_all(b"data: reload\n\n").is_ok()
+                    });
+                }
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("error: failed to accept connection: {e:?}");
+                continue;
+            }
+        };
+        let root = root.clone();
+        let clients = clients.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle(&root, stream, &clients) {
+                eprintln!("error: failed to handle request: {e:?}");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(
+    root: &Path,
+    mut stream: TcpStream,
+    clients: &Mutex<Vec<TcpStream>>,
+) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next(), parts.next().unwrap_or("/"));
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+    let response = if method != Some("GET") {
+        Response::new("405 Method Not Allowed", "text/plain", "")
+    } else if path == EVENTS_PATH {
+        stream.write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\nretry: 1000\n\n",
+        )?;
+        if let Ok(mut clients) = clients.lock() {
+            clients.push(stream);
+        }
+        return Ok(());
+    } else {
+        respond(root, path)
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    Ok(())
+}
+
+/// Generate the response of url `path` for files in `root`
+fn respond(root: &Path, path: &str) -> Response {
+    if let Some(name) = path.strip_prefix(ASSETS_PATH) {
+        return match ASSETS.iter().find(|(asset, ..)| *asset == name) {
+            Some((_, content_type, body)) => {
+                Response::new("200 OK", content_type, *body)
+            }
+            None => Response::not_found(),
+        };
+    }
+
+    let relpath = PathBuf::from(decode_path(path).trim_start_matches('/'));
+    if relpath
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Response::new("403 Forbidden", "text/plain", "");
+    }
+    let file = root.join(&relpath);
+    let markdown = file.with_extension("md");
+    if file.is_dir() {
+        if file.join("index.md").is_file() {
+            render_page(&file.join("index.md"))
+        } else {
+            render_index(root, &relpath)
+        }
+    } else if file.is_file() {
+        if file.extension().is_some_and(|ext| ext == "md") {
+            render_page(&file)
+        } else {
+            match fs::read(&file) {
+                Ok(data) => Response::new("200 OK", content_type(&file), data),
+                Err(_) => Response::not_found(),
+            }
+        }
+    } else if markdown.is_file() {
+        // `page.html` and `page` are also rendered from `page.md`
+        render_page(&markdown)
+    } else {
+        Response::not_found()
+    }
+}
+
+fn title(file: &Path, page: &Page) -> String {
+    match &page.meta {
+        Some(meta) if !meta.title.is_empty() => meta.title.clone(),
+        _ => file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn render_page(file: &Path) -> Response {
+    let Ok(content) = fs::read_to_string(file) else {
+        return Response::not_found();
+    };
+    match options_for_file(file) {
+        Ok(options) => {
+            let page = Page::new_with(content, options);
+            Response::html("200 OK", &title(file, &page), page.render())
+        }
+        Err(e) => Response::html(
+            "500 Internal Server Error",
+            "Error",
+            format!("<pre>{}</pre>", utils::escape_to_html(&format!("{e:?}"))),
+        ),
+    }
+}
+
+/// List sub directories and markdown files in directory `relpath` of `root`
+fn render_index(root: &Path, relpath: &Path) -> Response {
+    let Ok(entries) = fs::read_dir(root.join(relpath)) else {
+        return Response::not_found();
+    };
+    let mut entries = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<PathBuf>>();
+    entries.sort();
+
+    let name = relpath.display().to_string();
+    let mut html = format!("<h1>/{}</h1><ul>", utils::escape_to_html(&name));
+    for entry in entries {
+        let Some(name) = entry.file_name().map(|name| name.to_string_lossy())
+        else {
+            continue;
+        };
+        let url = relpath
+            .join(name.as_ref())
+            .components()
+            .map(|component| {
+                encode_path(&component.as_os_str().to_string_lossy())
+            })
+            .collect::<Vec<String>>()
+            .join("/");
+        let text = if entry.is_dir() {
+            format!("{name}/")
+        } else if entry.extension().is_some_and(|ext| ext == "md") {
+            match fs::read_to_string(&entry) {
+                Ok(content) => title(&entry, &Page::new(content)),
+                Err(_) => continue,
+            }
+        } else {
+            continue;
+        };
+        html.push_str(&format!(
+            r#"<li><a href="/{}">{}</a></li>"#,
+            utils::escape_html_double_quote(&url),
+            utils::escape_to_html(&text)
+        ));
+    }
+    html.push_str("</ul>");
+    Response::html("200 OK", &format!("/{name}"), html)
+}
+
+fn content_type(file: &Path) -> &'static str {
+    let ext = file
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Decode percent-encoded url path
+fn decode_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = path
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Percent-encode a url path segment
+fn encode_path(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Fingerprints of all files in `dir` recursively, hidden files are ignored
+fn snapshot(dir: &Path) -> HashMap<PathBuf, Fingerprint> {
+    let mut files = HashMap::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    {
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(snapshot(&path));
+        } else {
+            let current = fingerprint(&path);
+            files.insert(path, current);
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond() {
+        let root = std::env::temp_dir()
+            .join(format!("concisemark-serve-{}", std::process::id()));
+        fs::create_dir_all(root.join("guide")).unwrap();
+        fs::write(
+            root.join("guide/intro page.md"),
+            "<!---\ntitle = \"Intro <1>\"\ndate = \"2023-08-27 10:39:05\"\n-->\n# Intro\n",
+        )
+        .unwrap();
+        fs::write(root.join("logo.png"), "png").unwrap();
+        fs::write(
+            root.join("concisemark.toml"),
+            "[options]\nheading_ids = true\n",
+        )
+        .unwrap();
+
+        let body =
+            |response: Response| String::from_utf8(response.body).unwrap();
+        for path in [
+            "/guide/intro%20page.md",
+            "/guide/intro%20page.html",
+            "/guide/intro%20page",
+        ] {
+            let response = respond(&root, path);
+            assert_eq!(response.status, "200 OK");
+            let html = body(response);
+            assert!(html.contains("<title>Intro &lt;1&gt;</title>"));
+            assert!(html.contains("<h1 id=\"intro\">Intro"));
+            assert!(html.contains("/__concisemark/reload.js"));
+        }
+
+        let html = body(respond(&root, "/guide"));
+        assert!(html.contains(
+            r#"<li><a href="/guide/intro%20page.md">Intro &lt;1&gt;</a></li>"#
+        ));
+        let html = body(respond(&root, "/"));
+        assert!(html.contains(r#"<li><a href="/guide">guide/</a></li>"#));
+
+        let response = respond(&root, "/logo.png");
+        assert_eq!(response.content_type, "image/png");
+        assert_eq!(response.body, b"png");
+        let response = respond(&root, "/__concisemark/math.js");
+        assert_eq!(response.content_type, "text/javascript");
+        assert_eq!(
+            respond(&root, "/guide/../../etc/passwd").status,
+            "403 Forbidden"
+        );
+        assert_eq!(respond(&root, "/missing.md").status, "404 Not Found");
+
+        _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,257 @@
+//! Watch a source directory by polling and rebuild changed documents
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use concisemark::{
+    event::{Event, Events},
+    node::NodeTagName,
+};
+
+use crate::{
+    markdown_files, options_for_file, output_path, render_file, Format,
+};
+
+/// The modified time and size of a file, `None` if the file does not exist
+pub type Fingerprint = Option<(SystemTime, u64)>;
+
+pub fn fingerprint(path: &Path) -> Fingerprint {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+pub struct Watcher {
+    source: PathBuf,
+    output: PathBuf,
+    format: Format,
+    /// Fingerprints of markdown files and the local files they reference
+    files: HashMap<PathBuf, Fingerprint>,
+    /// Referenced file => markdown files referencing it
+    referrers: HashMap<PathBuf, HashSet<PathBuf>>,
+}
+
+impl Watcher {
+    pub fn new(source: PathBuf, output: PathBuf, format: Format) -> Self {
+        Self {
+            source,
+            output,
+            format,
+            files: HashMap::new(),
+            referrers: HashMap::new(),
+        }
+    }
+
+    /// Check files for changes and rebuild changed markdown files and markdown files referencing
+    /// changed files, returns rebuilt markdown files with their pretty printed diagnostics
+    ///
+    /// All markdown files are built in the first poll.
+    pub fn poll(&mut self) -> Result<Vec<(PathBuf, Result<Vec<String>>)>> {
+        let docs = markdown_files(&self.source)?
+            .into_iter()
+            .filter(|doc| !doc.starts_with(&self.output))
+            .collect::<BTreeSet<PathBuf>>();
+
+        let mut watched = docs.iter().cloned().collect::<HashSet<PathBuf>>();
+        watched.extend(self.referrers.keys().cloned());
+        watched.extend(self.files.keys().cloned());
+        let mut changed = vec![];
+        for path in watched {
+            let current = fingerprint(&path);
+            if self.files.get(&path) != Some(&current) {
+                changed.push(path.clone());
+            }
+            self.files.insert(path, current);
+        }
+
+        let mut dirty = BTreeSet::new();
+        for path in changed.iter() {
+            if docs.contains(path) {
+                dirty.insert(path.clone());
+            } else if path.extension().is_some_and(|ext| ext == "md") {
+                // the markdown file is removed
+                let target =
+                    output_path(&self.source, &self.output, path, self.format)?;
+                _ = fs::remove_file(target);
+                self.forget(path);
+            }
+            if let Some(referrers) = self.referrers.get(path) {
+                dirty.extend(
+                    referrers.iter().filter(|doc| docs.contains(*doc)).cloned(),
+                );
+            }
+        }
+
+        let mut rebuilt = vec![];
+        for doc in dirty {
+            // the config is loaded again since it may be changed
+            let result = options_for_file(&doc)
+                .and_then(|options| {
+                    render_file(
+                        &self.source,
+                        &self.output,
+                        &doc,
+                        self.format,
+                        options,
+                    )
+                })
+                .map(|(_, diagnostics)| diagnostics);
+            self.forget(&doc);
+            if let Ok(content) = fs::read_to_string(&doc) {
+                for reference in references(&doc, &content) {
+                    self.files
+                        .entry(reference.clone())
+                        .or_insert_with(|| fingerprint(&reference));
+                    self.referrers
+                        .entry(reference)
+                        .or_default()
+                        .insert(doc.clone());
+                }
+            }
+            rebuilt.push((doc, result));
+        }
+
+        let referrers = &self.referrers;
+        self.files.retain(|path, _| {
+            docs.contains(path) || referrers.contains_key(path)
+        });
+        Ok(rebuilt)
+    }
+
+    /// Remove `doc` from referrers
+    fn forget(&mut self, doc: &Path) {
+        for referrers in self.referrers.values_mut() {
+            referrers.remove(doc);
+        }
+        self.referrers.retain(|_, referrers| !referrers.is_empty());
+    }
+}
+
+/// Local files referenced by links and images in markdown file `doc`
+fn references(doc: &Path, content: &str) -> Vec<PathBuf> {
+    let dir = doc.parent().unwrap_or(Path::new(""));
+    Events::new(content)
+        .filter_map(|event| match event {
+            Event::Text(tag, _) => match tag.name {
+                NodeTagName::Link => tag.attrs.get("href").cloned(),
+                NodeTagName::Image => tag.attrs.get("src").cloned(),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter_map(|uri| {
+            let path = uri.split(['#', '?']).next()?.trim();
+            if path.is_empty() || path.contains(':') || path.starts_with('/') {
+                return None;
+            }
+            Some(normalize(&dir.join(path)))
+        })
+        .collect()
+}
+
+/// Normalize `path` lexically by removing `.` and resolving `..`
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Watch `source` and rebuild changed files into `output` every `interval` until interrupted
+pub fn run(
+    source: PathBuf,
+    output: PathBuf,
+    format: Format,
+    interval: Duration,
+) -> Result<()> {
+    println!("watching {} (press Ctrl-C to stop)", source.display());
+    let mut watcher = Watcher::new(source, output, format);
+    loop {
+        for (doc, result) in watcher.poll()? {
+            match result {
+                Ok(diagnostics) => {
+                    println!("rebuilt {}", doc.display());
+                    for diagnostic in diagnostics {
+                        eprint!("{diagnostic}");
+                    }
+                }
+                Err(e) => println!("error: {}: {e:?}", doc.display()),
+            }
+        }
+        thread::sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher() {
+        let root = std::env::temp_dir()
+            .join(format!("concisemark-watch-{}", std::process::id()));
+        let source = root.join("src");
+        let output = root.join("out");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::write(source.join("a.md"), "# A\n\n![logo](sub/logo.png)\n")
+            .unwrap();
+        fs::write(source.join("sub/b.md"), "# B\n\nSee [a](../a.md)\n")
+            .unwrap();
+        fs::write(source.join("sub/logo.png"), "png").unwrap();
+        fs::write(
+            source.join("concisemark.toml"),
+            "[options]\nheading_ids = true\n",
+        )
+        .unwrap();
+
+        let mut watcher =
+            Watcher::new(source.clone(), output.clone(), Format::Html);
+        let rebuilt = |watcher: &mut Watcher| {
+            watcher
+                .poll()
+                .unwrap()
+                .into_iter()
+                .map(|(doc, _)| doc)
+                .collect::<Vec<PathBuf>>()
+        };
+        assert_eq!(
+            rebuilt(&mut watcher),
+            vec![source.join("a.md"), source.join("sub/b.md")]
+        );
+        assert!(output.join("sub/b.html").exists());
+        assert!(fs::read_to_string(output.join("sub/b.html"))
+            .unwrap()
+            .contains("id=\"b\""));
+        assert!(rebuilt(&mut watcher).is_empty());
+
+        // b.md references a.md and a.md references logo.png
+        fs::write(source.join("sub/logo.png"), "new png").unwrap();
+        assert_eq!(rebuilt(&mut watcher), vec![source.join("a.md")]);
+        fs::write(source.join("a.md"), "# A\n").unwrap();
+        assert_eq!(
+            rebuilt(&mut watcher),
+            vec![source.join("a.md"), source.join("sub/b.md")]
+        );
+        fs::write(source.join("sub/logo.png"), "png again").unwrap();
+        assert!(rebuilt(&mut watcher).is_empty());
+
+        fs::remove_file(source.join("sub/b.md")).unwrap();
+        assert!(rebuilt(&mut watcher).is_empty());
+        assert!(!output.join("sub/b.html").exists());
+
+        _ = fs::remove_dir_all(root);
+    }
+}
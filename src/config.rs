@@ -0,0 +1,331 @@
+//! Project configuration
+//!
+//! A project can put a `concisemark.toml` in its root directory to build all documents with
+//! consistent settings, for example
+//!
+//! ```toml
+//! # directories relative to the project root
+//! source = "docs"
+//! output = "public"
+//! # files to skip, `*` matches any characters except `/` and `**` matches any characters
+//! ignore = ["drafts/**", "*.wip.md"]
+//!
+//! # default page options, see `PageOptions`
+//! [options]
+//! heading_ids = true
+//! latex_template = "templates/setup.tex"
+//!
+//! # options for documents in `docs/api` which are merged into the default options
+//! [overrides."api"]
+//! soft_break = "hard-break"
+//!
+//! # settings of static site generation, see `concisemark::site`
+//! [site]
+//! title = "Docs"
+//! template = "templates/page.html"
+//! tags_dir = "tags"
+//! url = "https://example.com/docs"
+//! search = true
+//! ```
+//!
+//! All fields are optional.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{site::SiteConfig, PageOptions};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Directory of markdown files
+    pub source: PathBuf,
+    /// Directory of rendered files
+    pub output: PathBuf,
+    /// Glob patterns of files or directories (relative to `source`) to skip
+    pub ignore: Vec<String>,
+    /// Default page options, relative paths in it are resolved by [`Config::options_for`]
+    pub options: PageOptions,
+    /// Directory (relative to `source`) => page options merged into the default options for
+    /// documents in it, deeper directories take precedence
+    pub overrides: BTreeMap<String, toml::value::Table>,
+    /// Site settings, see [`Site`](crate::site::Site)
+    pub site: SiteConfig,
+    /// The directory relative paths are resolved against
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            source: PathBuf::from("."),
+            output: PathBuf::from("public"),
+            ignore: vec![],
+            options: PageOptions::default(),
+            overrides: BTreeMap::new(),
+            site: SiteConfig::default(),
+            root: PathBuf::new(),
+        }
+    }
+}
+
+impl Config {
+    pub const FILE_NAME: &'static str = "concisemark.toml";
+
+    /// Parse config from toml `text`, relative paths are resolved against `root`
+    pub fn parse<P: AsRef<Path>>(text: &str, root: P) -> Result<Self> {
+        let mut config: Config =
+            toml::from_str(text).context("invalid config")?;
+        config.root = root.as_ref().to_path_buf();
+        config.source = config.root.join(&config.source);
+        config.output = config.root.join(&config.output);
+        Ok(config)
+    }
+
+    /// Load config from file `path`, relative paths are resolved against the directory of the file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let root = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&text, root)
+            .with_context(|| format!("failed to load {}", path.display()))
+    }
+
+    /// Find config file in `dir` and its ancestors
+    pub fn find<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+        dir.as_ref()
+            .ancestors()
+            .map(|dir| dir.join(Self::FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Whether file or directory `relpath` (relative to `source`) is ignored
+    pub fn is_ignored<P: AsRef<Path>>(&self, relpath: P) -> bool {
+        let relpath = to_slash(relpath.as_ref());
+        self.ignore.iter().any(|pattern| {
+            let pattern = pattern.trim_matches('/');
+            if pattern.contains('/') {
+                glob_match(pattern, &relpath)
+            } else {
+                // patterns without `/` match a file or directory name at any level
+                relpath.split('/').any(|name| glob_match(pattern, name))
+            }
+        })
+    }
+
+    /// Page options for document `relpath` (relative to `source`)
+    pub fn options_for<P: AsRef<Path>>(
+        &self,
+        relpath: P,
+    ) -> Result<PageOptions> {
+        let relpath = to_slash(relpath.as_ref());
+        let mut overrides = self
+            .overrides
+            .iter()
+            .map(|(dir, table)| (dir.trim_matches('/'), table))
+            .filter(|(dir, _)| {
+                dir.is_empty()
+                    || relpath
+                        .strip_prefix(dir)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .collect::<Vec<_>>();
+        if overrides.is_empty() {
+            return Ok(self.resolve(self.options.clone()));
+        }
+        overrides.sort_by_key(|(dir, _)| dir.split('/').count());
+
+        let mut options = toml::Value::try_from(&self.options)?;
+        if let toml::Value::Table(options) = &mut options {
+            for (_, table) in overrides {
+                merge(options, table);
+            }
+        }
+        let options = options
+            .try_into::<PageOptions>()
+            .with_context(|| format!("invalid options for {relpath}"))?;
+        Ok(self.resolve(options))
+    }
+
+    /// Markdown files in `source` which are not ignored in a stable order, files in `output` are
+    /// skipped
+    pub fn documents(&self) -> Result<Vec<PathBuf>> {
+        let mut documents = vec![];
+        self.collect_documents(&self.source, &mut documents)?;
+        Ok(documents)
+    }
+
+    fn collect_documents(
+        &self,
+        dir: &Path,
+        documents: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let mut entries = fs::read_dir(dir)
+            .with_context(|| format!("failed to read {}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()?;
+        entries.sort();
+        for path in entries {
+            let relpath = path.strip_prefix(&self.source)?;
+            if path.starts_with(&self.output) || self.is_ignored(relpath) {
+                continue;
+            }
+            if path.is_dir() {
+                self.collect_documents(&path, documents)?;
+            } else if path.extension().is_some_and(|ext| ext == "md") {
+                documents.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve relative paths in `options` against the root
+    fn resolve(&self, mut options: PageOptions) -> PageOptions {
+        if let Some(template) = &options.latex_template {
+            options.latex_template = Some(self.root.join(template));
+        }
+        if let Some(stylesheet) = &options.document.stylesheet {
+            options.document.stylesheet = Some(self.root.join(stylesheet));
+        }
+        options
+    }
+}
+
+/// Merge `table` into `base` recursively
+fn merge(base: &mut toml::value::Table, table: &toml::value::Table) {
+    for (key, value) in table {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(table)) => {
+                merge(base, table)
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Match `text` against glob `pattern`, `*` matches any characters except `/`, `**` matches any
+/// characters and `?` matches one character except `/`
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let text = text.chars().collect::<Vec<char>>();
+    // matched[j] is whether pattern[..i] matches text[..j]
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    let mut i = 0;
+    while i < pattern.len() {
+        let mut next = vec![false; text.len() + 1];
+        match pattern[i] {
+            '*' => {
+                let crossing = pattern.get(i + 1) == Some(&'*');
+                if crossing {
+                    i += 1;
+                }
+                for j in 0..=text.len() {
+                    next[j] = matched[j]
+                        || (j > 0
+                            && next[j - 1]
+                            && (crossing || text[j - 1] != '/'));
+                }
+            }
+            ch => {
+                for j in 1..=text.len() {
+                    next[j] = matched[j - 1]
+                        && (text[j - 1] == ch
+                            || (ch == '?' && text[j - 1] != '/'));
+                }
+            }
+        }
+        matched = next;
+        i += 1;
+    }
+    matched[text.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::options::SoftBreak;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.md", "a.md"));
+        assert!(!glob_match("*.md", "dir/a.md"));
+        assert!(glob_match("**/*.md", "dir/sub/a.md"));
+        assert!(glob_match("drafts/**", "drafts/a/b.md"));
+        assert!(glob_match("a?.md", "ab.md"));
+        assert!(!glob_match("a?.md", "a/.md"));
+    }
+
+    #[test]
+    fn test_config() {
+        let text = indoc! {r#"
+            source = "docs"
+            ignore = ["drafts/**", "*.wip.md"]
+
+            [options]
+            heading_ids = true
+            latex_template = "setup.tex"
+
+            [options.document]
+            stylesheet = "theme.css"
+
+            [overrides."api"]
+            soft_break = "hard-break"
+
+            [overrides."api/v1"]
+            heading_ids = false
+
+            [overrides."api/v1".extensions]
+            math = false
+        "#};
+        let config = Config::parse(text, "project").unwrap();
+        assert_eq!(config.source, Path::new("project/docs"));
+        assert_eq!(config.output, Path::new("project/public"));
+        assert!(config.is_ignored("drafts/a.md"));
+        assert!(config.is_ignored("guide/intro.wip.md"));
+        assert!(!config.is_ignored("guide/intro.md"));
+
+        let options = config.options_for("guide/intro.md").unwrap();
+        assert!(options.heading_ids);
+        assert_eq!(options.soft_break, SoftBreak::Space);
+        assert_eq!(
+            options.latex_template.as_deref(),
+            Some(Path::new("project/setup.tex"))
+        );
+        assert_eq!(
+            options.document.stylesheet.as_deref(),
+            Some(Path::new("project/theme.css"))
+        );
+
+        let options = config.options_for("api/v1/users.md").unwrap();
+        assert!(!options.heading_ids);
+        assert_eq!(options.soft_break, SoftBreak::HardBreak);
+        assert!(!options.extensions.math && options.extensions.marks);
+        assert_eq!(
+            options.latex_template.as_deref(),
+            Some(Path::new("project/setup.tex"))
+        );
+
+        // `api2` is not in `api`
+        let options = config.options_for("api2/a.md").unwrap();
+        assert_eq!(options.soft_break, SoftBreak::Space);
+    }
+}
@@ -0,0 +1,260 @@
+//! Structured diagnostics of a page
+//!
+//! Problems found when parsing a page, such as a malformed list or a broken meta, and problems
+//! which would affect the rendered output, such as an unsupported mark, are reported as
+//! [`Diagnostic`]s, see [`Page::diagnostics`](crate::Page::diagnostics).
+//!
+//!     use concisemark::Page;
+//!     use concisemark::diagnostic::Severity;
+//!
+//!     let page = Page::new("@video{movie.mp4}");
+//!     let diagnostics = page.diagnostics();
+//!     assert_eq!(diagnostics[0].severity, Severity::Warning);
+//!     assert_eq!(diagnostics[0].code, "unsupported-mark");
+//!     print!("{}", diagnostics[0].pretty(&page.content, Some("movie.md")));
+//!
+//! The printed diagnostic will be
+//!
+//! ```text
+//! warning[unsupported-mark]: mark `@video` is not supported, its value is rendered as plain text
+//!  --> movie.md:1:1
+//!   |
+//! 1 | @video{movie.mp4}
+//!   | ^^^^^^^^^^^^^^^^^
+//! ```
+use std::{fmt, ops::Range, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    node::{Node, NodeTagName},
+    options::PageOptions,
+    render::mark,
+    token::Mark,
+    utils,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The output may differ from what the author expects
+    Warning,
+    /// Some content is lost or ignored
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A stable kebab-case identifier of the problem, such as `malformed-list`
+    pub code: &'static str,
+    pub message: String,
+    /// Byte range of the offending text in the page content
+    pub range: Range<usize>,
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(
+        code: &'static str,
+        message: S,
+        range: Range<usize>,
+    ) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            range,
+        }
+    }
+
+    pub fn warning<S: Into<String>>(
+        code: &'static str,
+        message: S,
+        range: Range<usize>,
+    ) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code,
+            message: message.into(),
+            range,
+        }
+    }
+
+    /// Move the range forward by `offset` bytes
+    pub(crate) fn offset(mut self, offset: usize) -> Self {
+        self.range = (self.range.start + offset)..(self.range.end + offset);
+        self
+    }
+
+    /// Line and column number (both start from 1) of the range start in `content`
+    pub fn location<S: AsRef<str>>(&self, content: S) -> (usize, usize) {
+        let content = content.as_ref();
+        let start = self.range.start.min(content.len());
+        let line_start = content[..start].rfind('\n').map_or(0, |pos| pos + 1);
+        let line = content[..start].matches('\n').count() + 1;
+        let column = content[line_start..start].chars().count() + 1;
+        (line, column)
+    }
+
+    /// Format the diagnostic for terminals, the offending source line of `content` is shown with
+    /// carets under the range, `origin` is the file name of the page if any
+    pub fn pretty<S: AsRef<str>>(
+        &self,
+        content: S,
+        origin: Option<&str>,
+    ) -> String {
+        let content = content.as_ref();
+        let start = self.range.start.min(content.len());
+        let line_start = content[..start].rfind('\n').map_or(0, |pos| pos + 1);
+        let line_end = content[start..]
+            .find('\n')
+            .map_or(content.len(), |pos| start + pos);
+        let end = self.range.end.clamp(start, line_end);
+        let (line, column) = self.location(content);
+
+        let gutter = " ".repeat(line.to_string().len());
+        let padding =
+            " ".repeat(utils::display_width(&content[line_start..start]));
+        let carets =
+            "^".repeat(utils::display_width(&content[start..end]).max(1));
+        format!(
+            "{self}\n{gutter}--> {}:{line}:{column}\n{gutter} |\n{line} | {}\n{gutter} | {padding}{carets}\n",
+            origin.unwrap_or("<input>"),
+            &content[line_start..line_end],
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// The error returned when parsing a page in strict mode, see
+/// [`PageOptions::strict`](crate::PageOptions::strict)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Diagnostics found when parsing, there is at least one diagnostic
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} problem(s) found when parsing",
+            self.diagnostics.len()
+        )?;
+        for diagnostic in self.diagnostics.iter() {
+            write!(f, "\n{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Check problems of `node` which do not stop parsing but would affect the rendered output
+pub(crate) fn check(
+    node: &Node,
+    content: &str,
+    options: &PageOptions,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let nodedata = node.data.borrow();
+    let range = nodedata.range.clone();
+    match nodedata.tag.name {
+        NodeTagName::Extension => {
+            if let Some(mark) = Mark::new_from_str(&content[range.clone()]) {
+                if !mark::SUPPORTED_MARKS.contains(&mark.name.as_str()) {
+                    diagnostics.push(Diagnostic::warning(
+                        "unsupported-mark",
+                        format!(
+                            "mark `@{}` is not supported, its value is rendered as plain text",
+                            mark.name
+                        ),
+                        range.clone(),
+                    ));
+                }
+                if mark.name == "emoji" {
+                    for name in mark.value.trim().split(';') {
+                        if gh_emoji::get(name.trim()).is_none() {
+                            diagnostics.push(Diagnostic::warning(
+                                "unknown-emoji",
+                                format!("unknown emoji `{}`", name.trim()),
+                                range.clone(),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        NodeTagName::Image => {
+            // the working directory is not necessarily the directory of the document, relative
+            // paths are only checked when they are resolved against an image base
+            let src = node.get_attr_or("src", "");
+            let resolved = src.starts_with('/')
+                || options
+                    .image_base
+                    .as_deref()
+                    .is_some_and(|base| !base.is_empty());
+            let src = options.image_src(&src);
+            if resolved
+                && !src.contains("://")
+                && !src.starts_with("data:")
+                && !Path::new(&src).exists()
+            {
+                diagnostics.push(Diagnostic::warning(
+                    "missing-image",
+                    format!(
+                        "image `{src}` does not exist, it is replaced by a placeholder in LaTeX output"
+                    ),
+                    range,
+                ));
+            }
+        }
+        _ => {}
+    }
+    drop(nodedata);
+    for child in node.children().iter() {
+        diagnostics.extend(check(child, content, options));
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_pretty() {
+        let content = "# 标题\n\n中文 @video{a.mp4} text\n";
+        let start = content.find('@').unwrap();
+        let diagnostic = Diagnostic::warning(
+            "unsupported-mark",
+            "mark `@video` is not supported",
+            start..(start + "@video{a.mp4}".len()),
+        );
+        assert_eq!(diagnostic.location(content), (3, 4));
+        let wanted = indoc! {"
+            warning[unsupported-mark]: mark `@video` is not supported
+             --> a.md:3:4
+              |
+            3 | 中文 @video{a.mp4} text
+              |      ^^^^^^^^^^^^^
+        "};
+        assert_eq!(diagnostic.pretty(content, Some("a.md")), wanted);
+    }
+}
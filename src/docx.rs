@@ -0,0 +1,978 @@
+//! Word documents
+//!
+//! A [`Docx`] renders pages into an [Office Open XML] (`.docx`) package. Headings use the built-in
+//! heading styles, emphasis and code are formatted runs, lists are bulleted by a numbering
+//! definition, links are hyperlink relationships, local images are embedded and code blocks use a
+//! monospace paragraph style, for example
+//!
+//!     use concisemark::{docx::Docx, Page};
+//!
+//!     let page = Page::new("Some **bold** text\n\n- item");
+//!     let docx = Docx::from_page(&page, ".");
+//!     assert!(docx.document().contains(r#"<w:numId w:val="1"/>"#));
+//!     let bytes = docx.to_bytes().unwrap();
+//!     assert!(bytes.starts_with(b"PK"));
+//!
+//! The package looks like
+//!
+//! ```text
+//! [Content_Types].xml
+//! _rels/.rels
+//! docProps/core.xml
+//! word/document.xml
+//! word/styles.xml
+//! word/numbering.xml
+//! word/_rels/document.xml.rels
+//! word/media/image1.png
+//! ```
+//!
+//! Math is kept as raw TeX in italics.
+//!
+//! [Office Open XML]: https://ecma-international.org/publications-and-standards/standards/ecma-376/
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Cursor, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    meta::Meta,
+    node::{Emphasis, Node, NodeTagName},
+    options::{PageOptions, SanitizePolicy, SoftBreak},
+    render::{self, markdown::is_spaced, RenderType},
+    utils, Page,
+};
+
+const NAMESPACE: &str =
+    "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const RELATIONSHIPS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const HYPERLINK: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink";
+const IMAGE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+
+/// Indentation of a list level in twentieths of a point
+const INDENT: usize = 720;
+/// English metric units per pixel at 96 dpi
+const EMU_PER_PIXEL: u64 = 9525;
+/// Max width of images, which is 6 inches
+const MAX_IMAGE_WIDTH: u64 = 6 * 914400;
+/// Bullets of list levels
+const BULLETS: [&str; 3] = ["•", "◦", "▪"];
+
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:docDefaults><w:rPrDefault><w:rPr><w:sz w:val="22"/></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after="160"/></w:pPr></w:pPrDefault></w:docDefaults>
+<w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/></w:style>
+<w:style w:type="paragraph" w:styleId="Title"><w:name w:val="Title"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:jc w:val="center"/></w:pPr><w:rPr><w:sz w:val="56"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Subtitle"><w:name w:val="Subtitle"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:jc w:val="center"/></w:pPr><w:rPr><w:color w:val="595959"/><w:sz w:val="28"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading1"><w:name w:val="heading 1"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:spacing w:before="360"/><w:outlineLvl w:val="0"/></w:pPr><w:rPr><w:b/><w:sz w:val="32"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading2"><w:name w:val="heading 2"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:spacing w:before="240"/><w:outlineLvl w:val="1"/></w:pPr><w:rPr><w:b/><w:sz w:val="28"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading3"><w:name w:val="heading 3"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:spacing w:before="240"/><w:outlineLvl w:val="2"/></w:pPr><w:rPr><w:b/><w:sz w:val="26"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading4"><w:name w:val="heading 4"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:outlineLvl w:val="3"/></w:pPr><w:rPr><w:b/><w:sz w:val="24"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading5"><w:name w:val="heading 5"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:outlineLvl w:val="4"/></w:pPr><w:rPr><w:b/><w:i/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading6"><w:name w:val="heading 6"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:outlineLvl w:val="5"/></w:pPr><w:rPr><w:i/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Quote"><w:name w:val="Quote"/><w:basedOn w:val="Normal"/><w:pPr><w:ind w:left="720"/></w:pPr><w:rPr><w:i/><w:color w:val="404040"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="ListParagraph"><w:name w:val="List Paragraph"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:after="0"/></w:pPr></w:style>
+<w:style w:type="paragraph" w:styleId="Code"><w:name w:val="Code"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:after="0"/><w:shd w:val="clear" w:color="auto" w:fill="F2F2F2"/></w:pPr><w:rPr><w:rFonts w:ascii="Consolas" w:hAnsi="Consolas" w:cs="Consolas"/><w:sz w:val="20"/></w:rPr></w:style>
+<w:style w:type="character" w:styleId="CodeChar"><w:name w:val="Code Char"/><w:rPr><w:rFonts w:ascii="Consolas" w:hAnsi="Consolas" w:cs="Consolas"/><w:shd w:val="clear" w:color="auto" w:fill="F2F2F2"/></w:rPr></w:style>
+<w:style w:type="character" w:styleId="Hyperlink"><w:name w:val="Hyperlink"/><w:rPr><w:color w:val="0563C1"/><w:u w:val="single"/></w:rPr></w:style>
+</w:styles>
+"#;
+
+const PACKAGE_RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>
+</Relationships>
+"#;
+
+/// A relationship of the main document
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Relationship {
+    id: String,
+    kind: &'static str,
+    target: String,
+    /// Whether target is an external url
+    external: bool,
+}
+
+/// An image embedded into the package
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Image {
+    /// Path of the image in the package such as `media/image1.png`
+    path: String,
+    /// Relationship id
+    id: String,
+    /// Size in EMU
+    size: (u64, u64),
+    data: Vec<u8>,
+}
+
+pub struct Docx {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub date: Option<DateTime<Utc>>,
+    pub keywords: Vec<String>,
+    /// Paragraphs of the document body
+    body: String,
+    relationships: Vec<Relationship>,
+    images: Vec<Image>,
+    /// Image file => index of the image in `images`
+    image_indexes: HashMap<PathBuf, usize>,
+    /// Number of drawings which need unique ids
+    drawings: usize,
+    /// Number of pages added
+    pages: usize,
+}
+
+impl Default for Docx {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Docx {
+    pub fn new() -> Self {
+        Self {
+            title: None,
+            authors: vec![],
+            date: None,
+            keywords: vec![],
+            body: String::new(),
+            relationships: vec![
+                Relationship {
+                    id: "rId1".to_owned(),
+                    kind: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles",
+                    target: "styles.xml".to_owned(),
+                    external: false,
+                },
+                Relationship {
+                    id: "rId2".to_owned(),
+                    kind: "http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering",
+                    target: "numbering.xml".to_owned(),
+                    external: false,
+                },
+            ],
+            images: vec![],
+            image_indexes: HashMap::new(),
+            drawings: 0,
+            pages: 0,
+        }
+    }
+
+    /// Create a document of `page`, the title, subtitle and authors of its meta are put into the
+    /// document properties and a title block, relative image paths are resolved against `base`
+    pub fn from_page<P: AsRef<Path>>(page: &Page, base: P) -> Self {
+        let mut docx = Self::new();
+        if let Some(meta) = &page.meta {
+            docx = docx.with_meta(meta);
+            docx.body.push_str(&paragraph(
+                r#"<w:pStyle w:val="Title"/>"#,
+                &run(&meta.title, ""),
+            ));
+            if let Some(subtitle) = &meta.subtitle {
+                docx.body.push_str(&paragraph(
+                    r#"<w:pStyle w:val="Subtitle"/>"#,
+                    &run(subtitle, ""),
+                ));
+            }
+        }
+        docx.add(page, base);
+        docx
+    }
+
+    /// Set document properties from `meta`
+    pub fn with_meta(mut self, meta: &Meta) -> Self {
+        self.title = Some(meta.title.clone());
+        self.authors = meta.authors.clone().unwrap_or_default();
+        self.date = Some(meta.date);
+        self.keywords = meta.tags.clone().unwrap_or_default();
+        self
+    }
+
+    /// Append `page` to the document on a new page, relative image paths are resolved against
+    /// `base`
+    ///
+    /// Images which can not be read or are not PNG, JPEG or GIF are rendered as their alt text,
+    /// remote images are rendered as links.
+    pub fn add<P: AsRef<Path>>(&mut self, page: &Page, base: P) {
+        if self.pages > 0 {
+            self.body
+                .push_str(r#"<w:p><w:r><w:br w:type="page"/></w:r></w:p>"#);
+        }
+        let options = page.options();
+        let mut renderer = Renderer {
+            content: &page.content,
+            options: &options,
+            base: base.as_ref(),
+            docx: self,
+        };
+        let mut body = String::new();
+        renderer.generate_block(&page.ast, 0, &mut body);
+        self.body.push_str(&body);
+        self.pages += 1;
+    }
+
+    /// The main document `word/document.xml`
+    pub fn document(&self) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="{NAMESPACE}" xmlns:r="{RELATIONSHIPS}" xmlns:wp="http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing" xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture">
+<w:body>
+{}<w:sectPr><w:pgSz w:w="11906" w:h="16838"/><w:pgMar w:top="1440" w:right="1440" w:bottom="1440" w:left="1440" w:header="708" w:footer="708" w:gutter="0"/></w:sectPr>
+</w:body>
+</w:document>
+"#,
+            self.body
+        )
+    }
+
+    /// Write the package into `writer`
+    pub fn write<W: Write + Seek>(&self, writer: W) -> Result<()> {
+        let mut zip = ZipWriter::new(writer);
+        let deflated = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated);
+        let files = [
+            ("[Content_Types].xml", self.content_types()),
+            ("_rels/.rels", PACKAGE_RELATIONSHIPS.to_owned()),
+            ("docProps/core.xml", self.core()),
+            ("word/document.xml", self.document()),
+            ("word/styles.xml", STYLES.to_owned()),
+            ("word/numbering.xml", numbering()),
+            (
+                "word/_rels/document.xml.rels",
+                self.document_relationships(),
+            ),
+        ];
+        for (path, content) in files {
+            zip.start_file(path, deflated)?;
+            zip.write_all(content.as_bytes())?;
+        }
+        let stored = FileOptions::default()
+            .compression_method(CompressionMethod::Stored);
+        for image in &self.images {
+            zip.start_file(format!("word/{}", image.path), stored)?;
+            zip.write_all(&image.data)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    /// The package as bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Cursor::new(vec![]);
+        self.write(&mut bytes)?;
+        Ok(bytes.into_inner())
+    }
+
+    fn content_types(&self) -> String {
+        let mut defaults = vec![
+            (
+                "rels",
+                "application/vnd.openxmlformats-package.relationships+xml",
+            ),
+            ("xml", "application/xml"),
+        ];
+        for image in &self.images {
+            let ext = image.path.rsplit('.').next().unwrap_or_default();
+            if !defaults.iter().any(|(e, _)| *e == ext) {
+                defaults.push((ext, image_media_type(ext).unwrap_or_default()));
+            }
+        }
+        let defaults = defaults
+            .iter()
+            .map(|(ext, media_type)| {
+                format!(
+                    r#"<Default Extension="{ext}" ContentType="{media_type}"/>"#
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+{defaults}
+<Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>
+<Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/>
+<Override PartName="/word/numbering.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml"/>
+<Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>
+</Types>
+"#
+        )
+    }
+
+    /// Core properties `docProps/core.xml`
+    fn core(&self) -> String {
+        let mut properties = vec![];
+        if let Some(title) = &self.title {
+            properties.push(format!(
+                "<dc:title>{}</dc:title>",
+                utils::escape_to_xml(title)
+            ));
+        }
+        if !self.authors.is_empty() {
+            properties.push(format!(
+                "<dc:creator>{}</dc:creator>",
+                utils::escape_to_xml(&self.authors.join("; "))
+            ));
+        }
+        if !self.keywords.is_empty() {
+            properties.push(format!(
+                "<cp:keywords>{}</cp:keywords>",
+                utils::escape_to_xml(&self.keywords.join(", "))
+            ));
+        }
+        if let Some(date) = &self.date {
+            properties.push(format!(
+                r#"<dcterms:created xsi:type="dcterms:W3CDTF">{}</dcterms:created>"#,
+                date.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ));
+        }
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+{}
+</cp:coreProperties>
+"#,
+            properties.join("\n")
+        )
+    }
+
+    /// Relationships of the main document `word/_rels/document.xml.rels`
+    fn document_relationships(&self) -> String {
+        let relationships = self
+            .relationships
+            .iter()
+            .map(|relationship| {
+                format!(
+                    r#"<Relationship Id="{}" Type="{}" Target="{}"{}/>"#,
+                    relationship.id,
+                    relationship.kind,
+                    utils::escape_to_xml(&relationship.target),
+                    if relationship.external {
+                        r#" TargetMode="External""#
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+{relationships}
+</Relationships>
+"#
+        )
+    }
+
+    /// Add a relationship, returns its id
+    fn relate(
+        &mut self,
+        kind: &'static str,
+        target: &str,
+        external: bool,
+    ) -> String {
+        let id = format!("rId{}", self.relationships.len() + 1);
+        self.relationships.push(Relationship {
+            id: id.clone(),
+            kind,
+            target: target.to_owned(),
+            external,
+        });
+        id
+    }
+
+    /// Embed image `file`, returns its index or `None` if it can not be read
+    fn embed(&mut self, file: &Path) -> Option<usize> {
+        if let Some(index) = self.image_indexes.get(file) {
+            return Some(*index);
+        }
+        let ext = file.extension()?.to_str()?.to_lowercase();
+        let ext = if ext == "jpg" { "jpeg".to_owned() } else { ext };
+        if image_media_type(&ext).is_none() {
+            log::warn!("unsupported image type: {}", file.display());
+            return None;
+        }
+        let data = match fs::read(file) {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("failed to read image {}: {e}", file.display());
+                return None;
+            }
+        };
+        let size = image_extent(image_size(&data));
+        let path = format!("media/image{}.{ext}", self.images.len() + 1);
+        let id = self.relate(IMAGE, &path, false);
+        self.images.push(Image {
+            path,
+            id,
+            size,
+            data,
+        });
+        self.image_indexes
+            .insert(file.to_owned(), self.images.len() - 1);
+        Some(self.images.len() - 1)
+    }
+}
+
+struct Renderer<'a> {
+    content: &'a str,
+    options: &'a PageOptions,
+    /// Directory to resolve relative image paths
+    base: &'a Path,
+    docx: &'a mut Docx,
+}
+
+impl<'a> Renderer<'a> {
+    /// Render block `node` into paragraphs, `depth` is the number of list items it is in
+    fn generate_block(&mut self, node: &Node, depth: usize, xml: &mut String) {
+        let nodedata = node.data.borrow();
+        let body = &self.content[nodedata.range.clone()];
+        let indent = if depth > 0 {
+            format!(r#"<w:ind w:left="{}"/>"#, INDENT * depth)
+        } else {
+            String::new()
+        };
+        match nodedata.tag.name {
+            NodeTagName::Section | NodeTagName::ListBody => {
+                for child in node.children().iter() {
+                    self.generate_block(child, depth, xml);
+                }
+            }
+            NodeTagName::List => {
+                for item in node.children().iter() {
+                    for child in item.children().iter() {
+                        if child.data.borrow().tag.name == NodeTagName::ListHead
+                        {
+                            let properties = format!(
+                                r#"<w:pStyle w:val="ListParagraph"/><w:numPr><w:ilvl w:val="{}"/><w:numId w:val="1"/></w:numPr>"#,
+                                depth.min(8)
+                            );
+                            let runs = self.generate_inlines(child);
+                            xml.push_str(&paragraph(&properties, &runs));
+                        } else {
+                            self.generate_block(child, depth + 1, xml);
+                        }
+                    }
+                }
+            }
+            NodeTagName::Heading => {
+                let level = node
+                    .get_attr_or("level", "1")
+                    .parse::<usize>()
+                    .unwrap_or(1)
+                    .clamp(1, 6);
+                let runs = self.generate_inlines(node);
+                xml.push_str(&paragraph(
+                    &format!(r#"<w:pStyle w:val="Heading{level}"/>"#),
+                    &runs,
+                ));
+            }
+            NodeTagName::Code => {
+                let properties = format!(r#"<w:pStyle w:val="Code"/>{indent}"#);
+                for line in utils::remove_indent(body).trim_end().lines() {
+                    xml.push_str(&paragraph(&properties, &run(line, "")));
+                }
+            }
+            NodeTagName::BlankLine => {}
+            _ => {
+                let runs = self.generate_inlines(node);
+                if runs.is_empty() {
+                    return;
+                }
+                let is_quote = nodedata.tag.name == NodeTagName::Para
+                    && body.trim_start().starts_with('>');
+                let properties = if is_quote {
+                    format!(r#"<w:pStyle w:val="Quote"/>{indent}"#)
+                } else {
+                    indent
+                };
+                xml.push_str(&paragraph(&properties, &runs));
+            }
+        }
+    }
+
+    /// Render inline children of `node` into runs
+    fn generate_inlines(&mut self, node: &Node) -> String {
+        let is_quote = node.data.borrow().tag.name == NodeTagName::Para
+            && self.content[node.data.borrow().range.clone()]
+                .trim_start()
+                .starts_with('>');
+        let mut runs = Runs {
+            xml: String::new(),
+            previous: None,
+            line_start: true,
+            broken: false,
+            soft_break: self.options.soft_break,
+        };
+        for child in node.children().iter() {
+            let nodedata = child.data.borrow();
+            let body = &self.content[nodedata.range.clone()];
+            if nodedata.tag.name != NodeTagName::Text {
+                drop(nodedata);
+                self.generate_inline(child, &mut runs);
+                continue;
+            }
+            let count = body.split('\n').count();
+            for (i, mut line) in body.split('\n').enumerate() {
+                if i > 0 {
+                    runs.line_start = true;
+                    runs.broken = true;
+                }
+                if runs.line_start {
+                    line = line.trim_start();
+                    if is_quote {
+                        line =
+                            line.strip_prefix('>').unwrap_or(line).trim_start();
+                    }
+                }
+                if i + 1 < count {
+                    line = line.trim_end();
+                }
+                runs.push(line, &run(line, ""));
+            }
+        }
+        runs.xml
+    }
+
+    fn generate_inline(&mut self, node: &Node, runs: &mut Runs) {
+        let nodedata = node.data.borrow();
+        let body = &self.content[nodedata.range.clone()];
+        match nodedata.tag.name {
+            NodeTagName::Code => {
+                let code = body.trim_matches('`').trim();
+                runs.push(code, &run(code, r#"<w:rStyle w:val="CodeChar"/>"#));
+            }
+            NodeTagName::Math => {
+                let tex = body.trim_matches('$').trim();
+                runs.push(tex, &run(tex, "<w:i/>"));
+            }
+            NodeTagName::Emphasis(t) => {
+                let text = body.trim_matches('*');
+                let properties = match t {
+                    Emphasis::Italics => "<w:i/>",
+                    Emphasis::Bold => "<w:b/>",
+                };
+                runs.push(text, &run(text, properties));
+            }
+            NodeTagName::Link => {
+                let url = node.get_attr_or("href", "");
+                let mut name = node.get_attr_or("name", "");
+                if name.is_empty() {
+                    name = url.clone();
+                }
+                runs.push(&name, &self.hyperlink(&url, &name));
+            }
+            NodeTagName::Image => {
+                let alt = node.get_attr_or("name", "");
+                let src = node.get_attr_or("src", "");
+                if src.contains("://") {
+                    let name = if alt.is_empty() { &src } else { &alt };
+                    runs.push(name, &self.hyperlink(&src, name));
+                    return;
+                }
+                let image = if src.starts_with("data:") {
+                    None
+                } else {
+                    self.docx.embed(&self.base.join(&src))
+                };
+                match image {
+                    Some(index) => {
+                        let drawing = self.drawing(index, &alt);
+                        runs.push(&alt, &drawing);
+                    }
+                    None => runs.push(&alt, &run(&alt, "")),
+                }
+            }
+            NodeTagName::Extension => {
+                let value = render::mark::generate(
+                    body,
+                    RenderType::Text,
+                    SanitizePolicy::None,
+                )
+                .unwrap_or_else(|| body.to_owned());
+                let value = value.trim();
+                runs.push(value, &run(value, ""));
+            }
+            _ => runs.push(body, &run(body, "")),
+        }
+    }
+
+    fn hyperlink(&mut self, url: &str, name: &str) -> String {
+        let id = self.docx.relate(HYPERLINK, url, true);
+        format!(
+            r#"<w:hyperlink r:id="{id}">{}</w:hyperlink>"#,
+            run(name, r#"<w:rStyle w:val="Hyperlink"/>"#)
+        )
+    }
+
+    fn drawing(&mut self, index: usize, alt: &str) -> String {
+        self.docx.drawings += 1;
+        let n = self.docx.drawings;
+        let image = &self.docx.images[index];
+        let (cx, cy) = image.size;
+        let name = image.path.rsplit('/').next().unwrap_or_default();
+        format!(
+            r#"<w:r><w:drawing><wp:inline distT="0" distB="0" distL="0" distR="0"><wp:extent cx="{cx}" cy="{cy}"/><wp:docPr id="{n}" name="Picture {n}" descr="{alt}"/><a:graphic><a:graphicData uri="http://schemas.openxmlformats.org/drawingml/2006/picture"><pic:pic><pic:nvPicPr><pic:cNvPr id="{n}" name="{name}"/><pic:cNvPicPr/></pic:nvPicPr><pic:blipFill><a:blip r:embed="{id}"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill><pic:spPr><a:xfrm><a:off x="0" y="0"/><a:ext cx="{cx}" cy="{cy}"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></pic:spPr></pic:pic></a:graphicData></a:graphic></wp:inline></w:drawing></w:r>"#,
+            alt = utils::escape_to_xml(alt),
+            id = image.id,
+        )
+    }
+}
+
+/// Runs of a paragraph, line breaks in the source are joined according to [`SoftBreak`]
+struct Runs {
+    xml: String,
+    /// The last character of the text pushed
+    previous: Option<char>,
+    /// Whether the next text starts a source line
+    line_start: bool,
+    /// Whether there is a line break before the next text
+    broken: bool,
+    soft_break: SoftBreak,
+}
+
+impl Runs {
+    /// Push `xml` of `text`, nothing is pushed if `text` is empty
+    fn push(&mut self, text: &str, xml: &str) {
+        let Some(first) = text.chars().next() else {
+            return;
+        };
+        if let (true, Some(previous)) = (self.broken, self.previous) {
+            match self.soft_break {
+                SoftBreak::Space => {
+                    if is_spaced(previous) || is_spaced(first) {
+                        self.xml.push_str(&run(" ", ""));
+                    }
+                }
+                SoftBreak::Newline | SoftBreak::HardBreak => {
+                    self.xml.push_str("<w:r><w:br/></w:r>")
+                }
+            }
+        }
+        self.xml.push_str(xml);
+        self.previous = text.chars().last();
+        self.line_start = false;
+        self.broken = false;
+    }
+}
+
+/// A paragraph with `properties` (the content of `w:pPr`)
+fn paragraph(properties: &str, runs: &str) -> String {
+    if properties.is_empty() {
+        format!("<w:p>{runs}</w:p>\n")
+    } else {
+        format!("<w:p><w:pPr>{properties}</w:pPr>{runs}</w:p>\n")
+    }
+}
+
+/// A run of `text` with `properties` (the content of `w:rPr`)
+fn run(text: &str, properties: &str) -> String {
+    let text = utils::escape_to_xml(text);
+    if properties.is_empty() {
+        format!(r#"<w:r><w:t xml:space="preserve">{text}</w:t></w:r>"#)
+    } else {
+        format!(
+            r#"<w:r><w:rPr>{properties}</w:rPr><w:t xml:space="preserve">{text}</w:t></w:r>"#
+        )
+    }
+}
+
+/// The numbering definitions `word/numbering.xml`, numbering `1` is a bulleted list
+fn numbering() -> String {
+    let levels = (0..9)
+        .map(|level| {
+            format!(
+                r#"<w:lvl w:ilvl="{level}"><w:start w:val="1"/><w:numFmt w:val="bullet"/><w:lvlText w:val="{}"/><w:lvlJc w:val="left"/><w:pPr><w:ind w:left="{}" w:hanging="360"/></w:pPr></w:lvl>"#,
+                BULLETS[level % BULLETS.len()],
+                INDENT * (level + 1)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:numbering xmlns:w="{NAMESPACE}">
+<w:abstractNum w:abstractNumId="0">
+<w:multiLevelType w:val="hybridMultilevel"/>
+{levels}
+</w:abstractNum>
+<w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num>
+</w:numbering>
+"#
+    )
+}
+
+fn image_media_type(ext: &str) -> Option<&'static str> {
+    match ext {
+        "png" => Some("image/png"),
+        "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        _ => None,
+    }
+}
+
+/// Size in EMU of an image of `size` pixels, scaled down to the max width, 4:3 at max width if the
+/// size is unknown
+fn image_extent(size: Option<(u32, u32)>) -> (u64, u64) {
+    let Some((width, height)) = size.filter(|&(w, h)| w > 0 && h > 0) else {
+        return (MAX_IMAGE_WIDTH, MAX_IMAGE_WIDTH * 3 / 4);
+    };
+    let width = width as u64 * EMU_PER_PIXEL;
+    let height = height as u64 * EMU_PER_PIXEL;
+    if width > MAX_IMAGE_WIDTH {
+        // the product overflows u64 for sizes read from a malformed header
+        let height = height as u128 * MAX_IMAGE_WIDTH as u128 / width as u128;
+        (MAX_IMAGE_WIDTH, height as u64)
+    } else {
+        (width, height)
+    }
+}
+
+/// Size of a PNG, GIF or JPEG image in pixels
+fn image_size(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| {
+        Some(u16::from_be_bytes([*data.get(i)?, *data.get(i + 1)?]) as u32)
+    };
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let be32 = |i: usize| {
+            Some(u32::from_be_bytes(data.get(i..i + 4)?.try_into().ok()?))
+        };
+        return Some((be32(16)?, be32(20)?));
+    }
+    if data.starts_with(b"GIF8") {
+        let le16 = |i: usize| {
+            Some(u16::from_le_bytes([*data.get(i)?, *data.get(i + 1)?]) as u32)
+        };
+        return Some((le16(6)?, le16(8)?));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        let mut i = 2;
+        while *data.get(i)? == 0xFF {
+            let marker = *data.get(i + 1)?;
+            // start of frame markers, others are huffman and arithmetic coding tables
+            if (0xC0..=0xCF).contains(&marker)
+                && ![0xC4, 0xC8, 0xCC].contains(&marker)
+            {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            i += 2 + be16(i + 2)? as usize;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use indoc::indoc;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn test_docx() {
+        let root = std::env::temp_dir()
+            .join(format!("concisemark-docx-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        // a 1600x1200 png header
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(1600u32.to_be_bytes());
+        png.extend(1200u32.to_be_bytes());
+        fs::write(root.join("logo.png"), &png).unwrap();
+        let content = indoc! {r#"
+            <!---
+            title = "Report & Notes"
+            date = "2024-01-01 00:00:00"
+            authors = ["Alice", "Bob"]
+            -->
+            # Summary
+
+            Some **bold**, *italic* and `code`
+            on two lines, see [docs](https://example.com/?a=1&b=2).
+
+            ## Details
+
+            - first
+                - nested
+            - second
+
+                    let a = 1 < 2;
+
+            > quoted
+
+            ![logo](logo.png) ![missing](missing.png)
+        "#};
+        let page = Page::new(content);
+        let docx = Docx::from_page(&page, &root);
+        let bytes = docx.to_bytes().unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut files = HashMap::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let mut data = vec![];
+            file.read_to_end(&mut data).unwrap();
+            files.insert(file.name().to_owned(), data);
+        }
+        let mut names = files.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "docProps/core.xml",
+                "word/_rels/document.xml.rels",
+                "word/document.xml",
+                "word/media/image1.png",
+                "word/numbering.xml",
+                "word/styles.xml",
+            ]
+        );
+        let text = |name: &str| String::from_utf8(files[name].clone()).unwrap();
+        for name in names.iter().filter(|name| !name.ends_with(".png")) {
+            let xml = text(name);
+            roxmltree::Document::parse(&xml)
+                .unwrap_or_else(|e| panic!("{name}: {e}\n{xml}"));
+        }
+        assert!(text("[Content_Types].xml")
+            .contains(r#"<Default Extension="png" ContentType="image/png"/>"#));
+        assert!(text("docProps/core.xml")
+            .contains("<dc:title>Report &amp; Notes</dc:title>"));
+        assert!(text("docProps/core.xml")
+            .contains("<dc:creator>Alice; Bob</dc:creator>"));
+
+        let document = text("word/document.xml");
+        let paragraphs = document
+            .lines()
+            .filter(|line| line.starts_with("<w:p>"))
+            .collect::<Vec<_>>();
+        let r = |text: &str| run(text, "");
+        assert_eq!(paragraphs.len(), 10);
+        assert_eq!(
+            paragraphs[..9],
+            [
+                format!(
+                    r#"<w:p><w:pPr><w:pStyle w:val="Title"/></w:pPr>{}</w:p>"#,
+                    r("Report & Notes")
+                ),
+                format!(
+                    r#"<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr>{}</w:p>"#,
+                    r("Summary")
+                ),
+                format!(
+                    r#"<w:p>{}{}{}{}{}{}{}{}<w:hyperlink r:id="rId3">{}</w:hyperlink>{}</w:p>"#,
+                    r("Some "),
+                    run("bold", "<w:b/>"),
+                    r(", "),
+                    run("italic", "<w:i/>"),
+                    r(" and "),
+                    run("code", r#"<w:rStyle w:val="CodeChar"/>"#),
+                    r(" "),
+                    r("on two lines, see "),
+                    run("docs", r#"<w:rStyle w:val="Hyperlink"/>"#),
+                    r(".")
+                ),
+                format!(
+                    r#"<w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr>{}</w:p>"#,
+                    r("Details")
+                ),
+                format!(
+                    r#"<w:p><w:pPr><w:pStyle w:val="ListParagraph"/><w:numPr><w:ilvl w:val="0"/><w:numId w:val="1"/></w:numPr></w:pPr>{}</w:p>"#,
+                    r("first")
+                ),
+                format!(
+                    r#"<w:p><w:pPr><w:pStyle w:val="ListParagraph"/><w:numPr><w:ilvl w:val="1"/><w:numId w:val="1"/></w:numPr></w:pPr>{}</w:p>"#,
+                    r("nested")
+                ),
+                format!(
+                    r#"<w:p><w:pPr><w:pStyle w:val="ListParagraph"/><w:numPr><w:ilvl w:val="0"/><w:numId w:val="1"/></w:numPr></w:pPr>{}</w:p>"#,
+                    r("second")
+                ),
+                format!(
+                    r#"<w:p><w:pPr><w:pStyle w:val="Code"/><w:ind w:left="720"/></w:pPr>{}</w:p>"#,
+                    r("let a = 1 < 2;")
+                ),
+                format!(
+                    r#"<w:p><w:pPr><w:pStyle w:val="Quote"/></w:pPr>{}</w:p>"#,
+                    r("quoted")
+                ),
+            ]
+        );
+        // images which can not be read are rendered as their alt text
+        assert!(paragraphs[9].starts_with("<w:p><w:r><w:drawing>"));
+        assert!(paragraphs[9].ends_with(&format!(
+            "</w:drawing></w:r>{}{}</w:p>",
+            r(" "),
+            r("missing")
+        )));
+        assert!(document.contains(r#"<a:blip r:embed="rId4"/>"#));
+        // 1600 pixels is scaled down to 6 inches
+        assert!(document.contains(r#"<wp:extent cx="5486400" cy="4114800"/>"#));
+
+        let relationships = text("word/_rels/document.xml.rels");
+        assert!(relationships.contains(r#"<Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/?a=1&amp;b=2" TargetMode="External"/>"#));
+        assert!(relationships.contains(r#"<Relationship Id="rId4" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/image1.png"/>"#));
+
+        // all styles used are defined
+        let styles = text("word/styles.xml");
+        for style in [
+            "Title",
+            "Heading1",
+            "Heading2",
+            "ListParagraph",
+            "Code",
+            "CodeChar",
+            "Quote",
+            "Hyperlink",
+        ] {
+            assert!(
+                styles.contains(&format!(r#"w:styleId="{style}""#)),
+                "{style}"
+            );
+        }
+        let numbering = text("word/numbering.xml");
+        assert!(numbering.contains(r#"<w:lvl w:ilvl="1"><w:start w:val="1"/><w:numFmt w:val="bullet"/><w:lvlText w:val="◦"/>"#));
+        assert!(numbering.contains(
+            r#"<w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num>"#
+        ));
+    }
+
+    #[test]
+    fn test_image_size() {
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend([0x20, 0x01, 0x10, 0x00]);
+        assert_eq!(image_size(&gif), Some((288, 16)));
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00,
+            0x11, 0x08, 0x00, 0x30, 0x00, 0x40,
+        ];
+        assert_eq!(image_size(&jpeg), Some((64, 48)));
+        assert_eq!(image_size(b"text"), None);
+
+        assert_eq!(image_extent(Some((64, 48))), (64 * 9525, 48 * 9525));
+        assert_eq!(
+            image_extent(Some((1600, 1200))),
+            (MAX_IMAGE_WIDTH, MAX_IMAGE_WIDTH * 3 / 4)
+        );
+        assert_eq!(
+            image_extent(Some((u32::MAX, u32::MAX))),
+            (MAX_IMAGE_WIDTH, MAX_IMAGE_WIDTH)
+        );
+        assert_eq!(
+            image_extent(Some((0, 48))),
+            (MAX_IMAGE_WIDTH, MAX_IMAGE_WIDTH * 3 / 4)
+        );
+    }
+}
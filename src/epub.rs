@@ -0,0 +1,819 @@
+//! EPUB 3 e-books
+//!
+//! An [`Epub`] collects pages into chapters and packages them into an [EPUB 3] container. Each
+//! level-1 heading starts a new chapter, or each page is a chapter with
+//! [`Epub::with_page_chapters`]. Chapters are rendered by the HTML renderer, the navigation
+//! document is built from the headings, and local images are packaged into the container, for
+//! example
+//!
+//!     use concisemark::{epub::Epub, Page};
+//!
+//!     let page = Page::new(r#"<!---
+//!     title = "Handbook"
+//!     date = "2024-01-01 00:00:00"
+//!     authors = ["Alice"]
+//!     -->
+//!     ## Install
+//!
+//!     Run `cargo install concisemark`
+//!
+//!     ## Usage
+//!
+//!     ### Render
+//!     "#);
+//!     let epub = Epub::from_page(&page, ".");
+//!     let titles = epub.chapters().iter().map(|chapter| chapter.title.as_str()).collect::<Vec<_>>();
+//!     assert_eq!(titles, ["Install", "Usage"]);
+//!     let bytes = epub.to_bytes().unwrap();
+//!     assert!(bytes.starts_with(b"PK"));
+//!
+//! The container looks like
+//!
+//! ```text
+//! mimetype
+//! META-INF/container.xml
+//! OEBPS/content.opf
+//! OEBPS/nav.xhtml
+//! OEBPS/chapter-001.xhtml
+//! OEBPS/chapter-002.xhtml
+//! OEBPS/images/image-001.png
+//! ```
+//!
+//! [EPUB 3]: https://www.w3.org/TR/epub-33/
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fs,
+    io::{Cursor, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    meta::Meta,
+    node::{self, Node, NodeTagName},
+    options::{PageOptions, SanitizePolicy},
+    render, utils, Page,
+};
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+/// A chapter of the book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    /// Path of the chapter in the container such as `chapter-001.xhtml`
+    pub path: String,
+    /// Headings of the chapter as `(level, id, text)`, the heading starting the chapter is not
+    /// included
+    pub headings: Vec<(usize, String, String)>,
+    /// Rendered XHTML body
+    body: String,
+}
+
+/// An image packaged into the container
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Image {
+    /// Path of the image in the container such as `images/image-001.png`
+    path: String,
+    media_type: &'static str,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Images {
+    images: Vec<Image>,
+    /// Image file => path of the image in the container
+    paths: HashMap<PathBuf, String>,
+}
+
+impl Images {
+    /// Package image `file`, returns its path in the container or `None` if it can not be read
+    fn add(&mut self, file: &Path) -> Option<String> {
+        if let Some(path) = self.paths.get(file) {
+            return Some(path.clone());
+        }
+        let ext = file.extension()?.to_str()?.to_lowercase();
+        let media_type = match ext.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "svg" => "image/svg+xml",
+            "webp" => "image/webp",
+            _ => {
+                log::warn!("unsupported image type: {}", file.display());
+                return None;
+            }
+        };
+        let data = match fs::read(file) {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("failed to read image {}: {e}", file.display());
+                return None;
+            }
+        };
+        let path = format!("images/image-{:03}.{ext}", self.images.len() + 1);
+        self.images.push(Image {
+            path: path.clone(),
+            media_type,
+            data,
+        });
+        self.paths.insert(file.to_owned(), path.clone());
+        Some(path)
+    }
+}
+
+pub struct Epub {
+    pub title: String,
+    pub authors: Vec<String>,
+    /// Language tag such as `en` or `zh-CN`
+    pub language: String,
+    /// Unique identifier of the book, a urn is made from the title if it is `None`
+    pub identifier: Option<String>,
+    pub date: Option<DateTime<Utc>>,
+    pub description: Option<String>,
+    pub subjects: Vec<String>,
+    page_chapters: bool,
+    chapters: Vec<Chapter>,
+    images: Images,
+}
+
+impl Epub {
+    pub fn new<S: AsRef<str>>(title: S) -> Self {
+        Self {
+            title: title.as_ref().to_owned(),
+            authors: vec![],
+            language: "en".to_owned(),
+            identifier: None,
+            date: None,
+            description: None,
+            subjects: vec![],
+            page_chapters: false,
+            chapters: vec![],
+            images: Images::default(),
+        }
+    }
+
+    /// Create a book of `page` with its meta, relative image paths are resolved against `base`
+    pub fn from_page<P: AsRef<Path>>(page: &Page, base: P) -> Self {
+        let mut epub = Self::new("Untitled");
+        if let Some(meta) = &page.meta {
+            epub = epub.with_meta(meta);
+        }
+        epub.add(page, base);
+        epub
+    }
+
+    /// Set title, authors, date, description (subtitle) and subjects (tags) from `meta`
+    pub fn with_meta(mut self, meta: &Meta) -> Self {
+        if !meta.title.trim().is_empty() {
+            self.title = meta.title.trim().to_owned();
+        }
+        self.authors = meta.authors.clone().unwrap_or_default();
+        self.date = Some(meta.date);
+        self.description = meta.subtitle.clone();
+        self.subjects = meta.tags.clone().unwrap_or_default();
+        self
+    }
+
+    pub fn with_language<S: AsRef<str>>(mut self, language: S) -> Self {
+        self.language = language.as_ref().to_owned();
+        self
+    }
+
+    pub fn with_identifier<S: AsRef<str>>(mut self, identifier: S) -> Self {
+        self.identifier = Some(identifier.as_ref().to_owned());
+        self
+    }
+
+    /// Make each page added later a single chapter instead of splitting it by level-1 headings
+    pub fn with_page_chapters(mut self, page_chapters: bool) -> Self {
+        self.page_chapters = page_chapters;
+        self
+    }
+
+    pub fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
+    /// Add chapters of `page`, relative image paths are resolved against `base`
+    ///
+    /// A chapter starting with a level-1 heading is titled by the heading, otherwise it is titled
+    /// by [`Meta::title`] or its first heading. Images which can not be read are rendered as
+    /// their alt text, and remote images are rendered as links since EPUB readers do not load
+    /// them.
+    pub fn add<P: AsRef<Path>>(&mut self, page: &Page, base: P) {
+        let mut parts: Vec<Vec<Node>> = vec![vec![]];
+        for child in page.ast.children() {
+            if child.data.borrow().tag.name == NodeTagName::BlankLine {
+                continue;
+            }
+            if !self.page_chapters
+                && heading_level(&child) == Some(1)
+                && !parts.last().unwrap().is_empty()
+            {
+                parts.push(vec![]);
+            }
+            parts.last_mut().unwrap().push(child);
+        }
+
+        let mut options = page.options().into_owned();
+        // everything is escaped to keep chapters well-formed XML
+        options.sanitize = SanitizePolicy::Escape;
+        let writer = ChapterWriter {
+            content: &page.content,
+            options,
+            base: base.as_ref(),
+            images: RefCell::new(std::mem::take(&mut self.images)),
+            ids: RefCell::new(HashSet::new()),
+            headings: RefCell::new(vec![]),
+        };
+        for part in parts.into_iter().filter(|part| !part.is_empty()) {
+            writer.ids.borrow_mut().clear();
+            let body = part
+                .iter()
+                .map(|node| writer.html(node))
+                .collect::<Vec<String>>()
+                .join("\n");
+            let mut headings = writer.headings.take();
+            let title = if heading_level(&part[0]) == Some(1) {
+                headings.remove(0).2
+            } else {
+                page.meta
+                    .as_ref()
+                    .map(|meta| meta.title.trim().to_owned())
+                    .filter(|title| !title.is_empty())
+                    .or_else(|| headings.first().map(|h| h.2.clone()))
+                    .unwrap_or_else(|| {
+                        format!("Chapter {}", self.chapters.len() + 1)
+                    })
+            };
+            self.chapters.push(Chapter {
+                title,
+                path: format!("chapter-{:03}.xhtml", self.chapters.len() + 1),
+                headings,
+                body,
+            });
+        }
+        self.images = writer.images.into_inner();
+    }
+
+    /// Write the EPUB container into `writer`
+    pub fn write<W: Write + Seek>(&self, writer: W) -> Result<()> {
+        if self.chapters.is_empty() {
+            bail!("the book has no chapters");
+        }
+        let mut zip = ZipWriter::new(writer);
+        // the mimetype must be the first file and must not be compressed
+        let stored = FileOptions::default()
+            .compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated);
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+        zip.start_file("META-INF/container.xml", deflated)?;
+        zip.write_all(CONTAINER.as_bytes())?;
+        zip.start_file("OEBPS/content.opf", deflated)?;
+        zip.write_all(self.package().as_bytes())?;
+        zip.start_file("OEBPS/nav.xhtml", deflated)?;
+        zip.write_all(self.nav().as_bytes())?;
+        for chapter in &self.chapters {
+            zip.start_file(format!("OEBPS/{}", chapter.path), deflated)?;
+            zip.write_all(
+                self.xhtml(&chapter.title, &chapter.body, "").as_bytes(),
+            )?;
+        }
+        for image in &self.images.images {
+            zip.start_file(format!("OEBPS/{}", image.path), stored)?;
+            zip.write_all(&image.data)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    /// The EPUB container as bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Cursor::new(vec![]);
+        self.write(&mut bytes)?;
+        Ok(bytes.into_inner())
+    }
+
+    /// The package document `content.opf`
+    fn package(&self) -> String {
+        let identifier = self.identifier.clone().unwrap_or_else(|| {
+            format!("urn:concisemark:{}", utils::slugify(&self.title))
+        });
+        let mut metadata = vec![
+            format!(
+                r#"<dc:identifier id="uid">{}</dc:identifier>"#,
+                utils::escape_to_xml(&identifier)
+            ),
+            format!(
+                "<dc:title>{}</dc:title>",
+                utils::escape_to_xml(&self.title)
+            ),
+            format!(
+                "<dc:language>{}</dc:language>",
+                utils::escape_to_xml(&self.language)
+            ),
+        ];
+        for author in &self.authors {
+            metadata.push(format!(
+                "<dc:creator>{}</dc:creator>",
+                utils::escape_to_xml(author)
+            ));
+        }
+        if let Some(date) = &self.date {
+            metadata.push(format!("<dc:date>{}</dc:date>", timestamp(date)));
+        }
+        if let Some(description) = &self.description {
+            metadata.push(format!(
+                "<dc:description>{}</dc:description>",
+                utils::escape_to_xml(description)
+            ));
+        }
+        for subject in &self.subjects {
+            metadata.push(format!(
+                "<dc:subject>{}</dc:subject>",
+                utils::escape_to_xml(subject)
+            ));
+        }
+        metadata.push(format!(
+            r#"<meta property="dcterms:modified">{}</meta>"#,
+            timestamp(&self.date.unwrap_or_else(Utc::now))
+        ));
+
+        let mut manifest = vec![
+            r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#.to_owned(),
+        ];
+        let mut spine = vec![];
+        for chapter in &self.chapters {
+            let id = item_id(&chapter.path);
+            manifest.push(format!(
+                r#"<item id="{id}" href="{}" media-type="application/xhtml+xml"/>"#,
+                chapter.path
+            ));
+            spine.push(format!(r#"<itemref idref="{id}"/>"#));
+        }
+        for image in &self.images.images {
+            manifest.push(format!(
+                r#"<item id="{}" href="{}" media-type="{}"/>"#,
+                item_id(&image.path),
+                image.path,
+                image.media_type
+            ));
+        }
+
+        let lines = |items: Vec<String>| {
+            items
+                .iter()
+                .map(|item| format!("    {item}\n"))
+                .collect::<String>()
+        };
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid" xml:lang="{language}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+{metadata}  </metadata>
+  <manifest>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#,
+            language = utils::escape_to_xml(&self.language),
+            metadata = lines(metadata),
+            manifest = lines(manifest),
+            spine = lines(spine),
+        )
+    }
+
+    /// The navigation document `nav.xhtml`, chapters are listed with their headings nested
+    fn nav(&self) -> String {
+        let mut toc = String::new();
+        for chapter in &self.chapters {
+            let headings = chapter
+                .headings
+                .iter()
+                .map(|(level, id, text)| {
+                    (*level, format!("{}#{id}", chapter.path), text.as_str())
+                })
+                .collect::<Vec<_>>();
+            toc.push_str(&format!(
+                "<li><a href=\"{}\">{}</a>{}</li>\n",
+                chapter.path,
+                utils::escape_to_xml(&chapter.title),
+                nav_list(&headings)
+            ));
+        }
+        let body = format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n{toc}</ol>\n</nav>",
+            utils::escape_to_xml(&self.title)
+        );
+        self.xhtml(
+            &self.title,
+            &body,
+            r#" xmlns:epub="http://www.idpf.org/2007/ops""#,
+        )
+    }
+
+    /// An XHTML content document, `namespaces` are added to the `html` element
+    fn xhtml(&self, title: &str, body: &str, namespaces: &str) -> String {
+        let language = utils::escape_to_xml(&self.language);
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml"{namespaces} lang="{language}" xml:lang="{language}">
+<head>
+<title>{}</title>
+</head>
+<body>
+{body}
+</body>
+</html>
+"#,
+            utils::escape_to_xml(title)
+        )
+    }
+}
+
+/// Renders nodes into XHTML and collects headings and images
+struct ChapterWriter<'a> {
+    content: &'a str,
+    options: PageOptions,
+    /// Directory to resolve relative image paths
+    base: &'a Path,
+    images: RefCell<Images>,
+    /// Heading ids of the current chapter
+    ids: RefCell<HashSet<String>>,
+    /// `(level, id, text)` of headings rendered since last taken
+    headings: RefCell<Vec<(usize, String, String)>>,
+}
+
+impl<'a> ChapterWriter<'a> {
+    /// Render `node` into XHTML, characters not allowed in XML such as control characters in the
+    /// content are dropped
+    fn html(&self, node: &Node) -> String {
+        render::html::generate(
+            node,
+            self.content,
+            &self.options,
+            Some(&|node: &Node| self.hook(node)),
+        )
+        .chars()
+        .filter(|&ch| utils::is_xml_char(ch))
+        .collect()
+    }
+
+    /// Render links and images whose urls must be escaped for XML (images are packaged as well),
+    /// headings are rendered here to make sure they have ids for the navigation document
+    fn hook(&self, node: &Node) -> Option<String> {
+        let nodedata = node.data.borrow();
+        match nodedata.tag.name {
+            NodeTagName::Heading => {
+                let level = heading_level(node).unwrap_or(1).clamp(1, 6);
+                let text = node::inline_text(node, self.content);
+                let mut ids = self.ids.borrow_mut();
+                let id = match nodedata.tag.attrs.get("id") {
+                    Some(id) => id.clone(),
+                    None => {
+                        let slug = utils::slugify(&text);
+                        let mut id = slug.clone();
+                        let mut count = 0;
+                        while ids.contains(&id) {
+                            count += 1;
+                            id = format!("{slug}-{count}");
+                        }
+                        id
+                    }
+                };
+                ids.insert(id.clone());
+                drop(ids);
+                self.headings.borrow_mut().push((level, id.clone(), text));
+                let inner = node
+                    .children()
+                    .iter()
+                    .filter(|child| {
+                        !self.content[child.data.borrow().range.clone()]
+                            .trim()
+                            .is_empty()
+                    })
+                    .map(|child| self.html(child))
+                    .collect::<String>();
+                Some(format!(
+                    r#"<h{level} id="{}">{inner}</h{level}>"#,
+                    utils::escape_to_xml(&id)
+                ))
+            }
+            NodeTagName::Link => {
+                let url = node.get_attr_or("href", "");
+                let mut name = node.get_attr_or("name", "");
+                if name.is_empty() {
+                    name = url.clone();
+                }
+                Some(format!(
+                    r#"<a href="{}">{}</a>"#,
+                    utils::escape_to_xml(&url),
+                    utils::escape_to_xml(&name)
+                ))
+            }
+            NodeTagName::Image => Some(self.image(node)),
+            _ => None,
+        }
+    }
+
+    fn image(&self, node: &Node) -> String {
+        let alt = node.get_attr_or("name", "");
+        let src = node.get_attr_or("src", "");
+        let img = |src: &str| {
+            format!(
+                r#"<img alt="{}" src="{}"/>"#,
+                utils::escape_to_xml(&alt),
+                utils::escape_to_xml(src)
+            )
+        };
+        if src.starts_with("data:") {
+            return img(&src);
+        }
+        if src.contains("://") {
+            let name = if alt.is_empty() { &src } else { &alt };
+            return format!(
+                r#"<a href="{}">{}</a>"#,
+                utils::escape_to_xml(&src),
+                utils::escape_to_xml(name)
+            );
+        }
+        match self.images.borrow_mut().add(&self.base.join(&src)) {
+            Some(path) => img(&path),
+            None => utils::escape_to_xml(&alt),
+        }
+    }
+}
+
+fn heading_level(node: &Node) -> Option<usize> {
+    if node.data.borrow().tag.name != NodeTagName::Heading {
+        return None;
+    }
+    node.get_attr_or("level", "1").parse().ok()
+}
+
+/// Nested `<ol>` of `(level, href, text)`, a deeper heading is nested in the previous one
+fn nav_list(items: &[(usize, String, &str)]) -> String {
+    let mut html = String::new();
+    // levels of open lists
+    let mut levels: Vec<usize> = vec![];
+    for (level, href, text) in items {
+        while levels.len() > 1 && levels.last().is_some_and(|l| level < l) {
+            html.push_str("</li></ol>");
+            levels.pop();
+        }
+        match levels.last() {
+            Some(l) if l >= level => {
+                html.push_str("</li>");
+                // a heading shallower than the first one is its sibling
+                *levels.last_mut().unwrap() = *level;
+            }
+            _ => {
+                html.push_str("<ol>");
+                levels.push(*level);
+            }
+        }
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            utils::escape_to_xml(href),
+            utils::escape_to_xml(text)
+        ));
+    }
+    for _ in levels {
+        html.push_str("</li></ol>");
+    }
+    html
+}
+
+/// Manifest item id of a file in the container, which is its file stem
+fn item_id(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.split('.').next().unwrap_or(name)
+}
+
+fn timestamp(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use indoc::indoc;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn test_epub() {
+        let root = std::env::temp_dir()
+            .join(format!("concisemark-epub-{}", std::process::id()));
+        fs::create_dir_all(root.join("images")).unwrap();
+        fs::write(root.join("images/logo.png"), "png").unwrap();
+        let content = indoc! {r#"
+            <!---
+            title = "Handbook"
+            subtitle = "All about <it>"
+            date = "2024-01-01 00:00:00"
+            authors = ["Alice", "Bob"]
+            tags = ["guide"]
+            -->
+            Read this first.
+
+            # Install
+
+            Get it [here](https://example.com/?a=1&b=2) ![logo](images/logo.png)
+
+                if a < b && c { }
+
+            ## From source
+
+            ### Linux
+
+            ## Binary
+
+            ![logo again](images/logo.png) ![remote](https://example.com/a.png) ![missing](missing.png)
+
+            # Usage
+
+            - item
+        "#};
+        // control characters are not allowed in XML
+        let content = content
+            .replace("this first", "this\u{0} fir\u{1b}st")
+            .replace("< b", "<\u{7} b");
+        let page = Page::new(content);
+        let epub = Epub::from_page(&page, &root).with_language("en-US");
+        let bytes = epub.to_bytes().unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let names = archive.file_names().map(str::to_owned).collect::<Vec<_>>();
+        let mut files = HashMap::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            if i == 0 {
+                assert_eq!(file.compression(), CompressionMethod::Stored);
+            }
+            let mut data = vec![];
+            file.read_to_end(&mut data).unwrap();
+            files.insert(file.name().to_owned(), data);
+        }
+        let mut names = names;
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "META-INF/container.xml",
+                "OEBPS/chapter-001.xhtml",
+                "OEBPS/chapter-002.xhtml",
+                "OEBPS/chapter-003.xhtml",
+                "OEBPS/content.opf",
+                "OEBPS/images/image-001.png",
+                "OEBPS/nav.xhtml",
+                "mimetype",
+            ]
+        );
+        assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+        assert_eq!(files["mimetype"], b"application/epub+zip");
+        assert_eq!(files["OEBPS/images/image-001.png"], b"png");
+
+        let xml_options = roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        };
+        let text = |name: &str| String::from_utf8(files[name].clone()).unwrap();
+        // all documents are well-formed XML
+        for name in files.keys().filter(|name| !name.ends_with(".png")) {
+            if name != "mimetype" {
+                let xml = text(name);
+                roxmltree::Document::parse_with_options(&xml, xml_options)
+                    .unwrap_or_else(|e| panic!("{name}: {e}\n{xml}"));
+            }
+        }
+
+        // every manifest item exists and the spine refers to manifest items
+        let opf = text("OEBPS/content.opf");
+        let package = roxmltree::Document::parse(&opf).unwrap();
+        let items = package
+            .descendants()
+            .filter(|node| node.has_tag_name("item"))
+            .map(|node| {
+                (
+                    node.attribute("id").unwrap(),
+                    node.attribute("href").unwrap(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        assert_eq!(items.len(), 5);
+        for href in items.values() {
+            assert!(files.contains_key(&format!("OEBPS/{href}")), "{href}");
+        }
+        let spine = package
+            .descendants()
+            .filter(|node| node.has_tag_name("itemref"))
+            .map(|node| items[node.attribute("idref").unwrap()])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spine,
+            [
+                "chapter-001.xhtml",
+                "chapter-002.xhtml",
+                "chapter-003.xhtml"
+            ]
+        );
+        assert!(opf.contains(r#"<dc:title>Handbook</dc:title>"#));
+        assert!(opf.contains("<dc:creator>Alice</dc:creator>"));
+        assert!(opf
+            .contains("<dc:description>All about &lt;it&gt;</dc:description>"));
+        assert!(opf.contains("<dc:language>en-US</dc:language>"));
+        assert!(opf.contains(
+            r#"<meta property="dcterms:modified">2024-01-01T00:00:00Z</meta>"#
+        ));
+
+        // the navigation document links to chapters and headings which exist
+        let nav = text("OEBPS/nav.xhtml");
+        let nav =
+            roxmltree::Document::parse_with_options(&nav, xml_options).unwrap();
+        let links = nav
+            .descendants()
+            .filter(|node| node.has_tag_name("a"))
+            .map(|node| {
+                let depth =
+                    node.ancestors().filter(|a| a.has_tag_name("ol")).count();
+                (depth, node.attribute("href").unwrap(), node.text().unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            [
+                (1, "chapter-001.xhtml", "Handbook"),
+                (1, "chapter-002.xhtml", "Install"),
+                (2, "chapter-002.xhtml#from-source", "From source"),
+                (3, "chapter-002.xhtml#linux", "Linux"),
+                (2, "chapter-002.xhtml#binary", "Binary"),
+                (1, "chapter-003.xhtml", "Usage"),
+            ]
+        );
+        for (_, href, _) in links {
+            let (path, id) = href.split_once('#').unwrap_or((href, ""));
+            let chapter = text(&format!("OEBPS/{path}"));
+            if !id.is_empty() {
+                assert!(chapter.contains(&format!(r#"id="{id}""#)), "{href}");
+            }
+        }
+
+        let chapter = text("OEBPS/chapter-002.xhtml");
+        assert!(chapter.contains("<title>Install</title>"));
+        assert!(chapter.contains(r#"<h1 id="install">Install"#));
+        assert!(chapter.contains(
+            r#"<a href="https://example.com/?a=1&amp;b=2">here</a>"#
+        ));
+        assert!(chapter
+            .contains("<pre><code>if a &lt; b &amp;&amp; c { }</code></pre>"));
+        assert!(
+            chapter.contains(r#"<img alt="logo" src="images/image-001.png"/>"#)
+        );
+        assert!(chapter
+            .contains(r#"<img alt="logo again" src="images/image-001.png"/>"#));
+        assert!(chapter
+            .contains(r#"<a href="https://example.com/a.png">remote</a>"#));
+        assert!(!chapter.contains("missing.png"));
+    }
+
+    #[test]
+    fn test_epub_page_chapters() {
+        let mut epub = Epub::new("Book").with_page_chapters(true);
+        assert!(epub.to_bytes().is_err());
+        epub.add(&Page::new("# One\n\n## A\n\n# Two"), ".");
+        epub.add(&Page::new("Untitled page"), ".");
+        let chapters = epub
+            .chapters()
+            .iter()
+            .map(|chapter| (chapter.title.as_str(), chapter.headings.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(chapters, [("One", 2), ("Chapter 2", 0)]);
+        assert_eq!(
+            nav_list(&[
+                (3, "a".to_owned(), "A"),
+                (2, "b".to_owned(), "B"),
+                (3, "c".to_owned(), "C"),
+            ]),
+            "<ol><li><a href=\"a\">A</a></li><li><a href=\"b\">B</a><ol><li><a href=\"c\">C</a></li></ol></li></ol>"
+        );
+    }
+}
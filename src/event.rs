@@ -0,0 +1,506 @@
+//! Pull parser event stream
+//!
+//! [`Events`] walks a markdown document and yields a flat stream of [`Event`]s without building
+//! the [`Node`](crate::node::Node) tree, which is cheap for large documents and easy to
+//! filter or map, for example
+//!
+//!     use concisemark::event::{Event, Events};
+//!     use concisemark::node::NodeTagName;
+//!
+//!     let content = "# Title\n\nSee [google](https://google.com)";
+//!     let links = Events::new(content)
+//!         .filter_map(|event| match event {
+//!             Event::Text(tag, _) if tag.name == NodeTagName::Link => {
+//!                 tag.attrs.get("href").cloned()
+//!             }
+//!             _ => None,
+//!         })
+//!         .collect::<Vec<String>>();
+//!     assert_eq!(links, vec!["https://google.com".to_owned()]);
+//!
+//! The [`Page`](crate::Page) AST is built on top of this event stream.
+use std::{collections::VecDeque, ops::Range, vec};
+
+use crate::{
+    diagnostic::Diagnostic,
+    meta::Meta,
+    node::{Emphasis, NodeTag, NodeTagName},
+    options::Extensions,
+    token::{Link, List, ListItem, Mark, MarkScan, Pair, Token, Tokenizer},
+};
+
+/// A parsing event
+///
+/// Like the AST, an event does not store any text but only the range of the node, the text can be
+/// retrived by indexing into the markdown content with the range.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Start of a node which may contain children, such as [`NodeTagName::Para`]
+    Start(NodeTag, Range<usize>),
+    /// A node without any children, such as [`NodeTagName::Text`] or [`NodeTagName::Math`]
+    Text(NodeTag, Range<usize>),
+    /// End of the node started by the last unmatched [`Event::Start`]
+    End(NodeTagName),
+}
+
+enum Frame<'a> {
+    /// A sequence of blocks such as paragraphs, headings and lists
+    Document {
+        tokenizer: Tokenizer<'a>,
+        pbase: usize,
+    },
+    /// Inline statements of a paragraph, heading or list head
+    Statements { cursor: usize, end: usize },
+    /// Items of a list
+    List {
+        pbase: usize,
+        items: vec::IntoIter<ListItem>,
+    },
+    /// A list item whose head has been emitted, `body` is taken once its head is done
+    ListItem { body: Option<(Range<usize>, usize)> },
+}
+
+/// An iterator of [`Event`]s over markdown content
+pub struct Events<'a> {
+    content: &'a str,
+    stack: Vec<(NodeTagName, Frame<'a>)>,
+    pending: VecDeque<Event>,
+    diagnostics: Vec<Diagnostic>,
+    extensions: Extensions,
+}
+
+impl<'a> Events<'a> {
+    /// Create an event stream from `content`, the optional meta in the front is skipped
+    pub fn new(content: &'a str) -> Self {
+        match Meta::parse(content) {
+            Ok(meta) => {
+                Self::with_base(content, meta.map_or(0, |meta| meta.size))
+            }
+            Err(diagnostic) => {
+                let mut events = Self::with_base(content, 0);
+                events.diagnostics.push(diagnostic);
+                events
+            }
+        }
+    }
+
+    /// Only parse the enabled syntax `extensions`, disabled syntax is parsed as text
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Diagnostics found so far, all diagnostics are available once the stream is exhausted
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Create an event stream for `content[pbase..]`, the ranges of events are still relative to
+    /// the start of `content`
+    pub(crate) fn with_base(content: &'a str, pbase: usize) -> Self {
+        let mut events = Self {
+            content,
+            stack: vec![],
+            pending: VecDeque::new(),
+            diagnostics: vec![],
+            extensions: Extensions::default(),
+        };
+        let range = pbase..content.len();
+        events.pending.push_back(Event::Start(
+            NodeTag::new(NodeTagName::Section),
+            range.clone(),
+        ));
+        events.push_document(NodeTagName::Section, range, 0);
+        events
+    }
+
+    fn push_document(
+        &mut self,
+        name: NodeTagName,
+        range: Range<usize>,
+        indent: usize,
+    ) {
+        let tokenizer = Tokenizer::new(&self.content[range.clone()], indent);
+        self.stack.push((
+            name,
+            Frame::Document {
+                tokenizer,
+                pbase: range.start,
+            },
+        ));
+    }
+
+    fn start_token(&mut self, pbase: usize, token: Token) -> Event {
+        let range = pbase..(pbase + token.len());
+        match token {
+            Token::Paragraph(_) => {
+                self.stack.push((
+                    NodeTagName::Para,
+                    Frame::Statements {
+                        cursor: range.start,
+                        end: range.end,
+                    },
+                ));
+                Event::Start(NodeTag::new(NodeTagName::Para), range)
+            }
+            Token::Heading(heading) => {
+                let value = heading.prop.val.as_str();
+                let heading_stmt = value.trim_start_matches('#');
+                // heading level should between h1 to h6, see (here)[https://developer.mozilla.org/en-US/docs/Web/HTML/Element/Heading_Elements]
+                let heading_level = match value.len() - heading_stmt.len() {
+                    0..=1 => 1,
+                    level @ 2..=6 => level,
+                    _ => 6,
+                };
+                self.stack.push((
+                    NodeTagName::Heading,
+                    Frame::Statements {
+                        cursor: range.start
+                            + (value.len() - heading_stmt.len()),
+                        end: range.end,
+                    },
+                ));
+                let tag = NodeTag::new(NodeTagName::Heading)
+                    .with_attr("level", heading_level.to_string());
+                Event::Start(tag, range)
+            }
+            Token::List(list) => {
+                let mut iter = list.iter();
+                let items = iter.by_ref().collect::<Vec<ListItem>>();
+                if let Some(diagnostic) = iter.take_diagnostic() {
+                    self.diagnostics.push(diagnostic.offset(pbase));
+                }
+                self.stack.push((
+                    NodeTagName::List,
+                    Frame::List {
+                        pbase,
+                        items: items.into_iter(),
+                    },
+                ));
+                Event::Start(NodeTag::new(NodeTagName::List), range)
+            }
+            Token::Codeblock(_) => {
+                Event::Text(NodeTag::new(NodeTagName::Code), range)
+            }
+            Token::BlankLine(_) => {
+                Event::Text(NodeTag::new(NodeTagName::BlankLine), range)
+            }
+        }
+    }
+
+    fn start_list_item(&mut self, pbase: usize, item: ListItem) -> Event {
+        let head = (pbase + item.head.start)..(pbase + item.head.end);
+        let body = (pbase + item.body.start)..(pbase + item.body.end);
+        let head_content = &self.content[head.clone()];
+        let list_indent = head_content.len() - head_content.trim_start().len();
+
+        self.stack.push((
+            NodeTagName::ListItem,
+            Frame::ListItem {
+                body: Some((body.clone(), item.indent)),
+            },
+        ));
+        self.stack.push((
+            NodeTagName::ListHead,
+            Frame::Statements {
+                cursor: head.start + list_indent + List::LIST_MARK.len(),
+                end: head.end,
+            },
+        ));
+        self.pending
+            .push_back(Event::Start(NodeTag::new(NodeTagName::ListHead), head));
+        Event::Start(
+            NodeTag::new(NodeTagName::ListItem),
+            (pbase + item.head.start)..body.end,
+        )
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+
+        let content = self.content;
+        let (name, frame) = self.stack.last_mut()?;
+        let name = *name;
+        match frame {
+            Frame::Document { tokenizer, pbase } => {
+                if let Some(token) = tokenizer.next() {
+                    let start = *pbase;
+                    *pbase += token.len();
+                    return Some(self.start_token(start, token));
+                }
+            }
+            Frame::Statements { cursor, end } => {
+                if *cursor < *end {
+                    let mut diagnostics = vec![];
+                    let (tag, size) = parse_statement(
+                        &content[*cursor..*end],
+                        &self.extensions,
+                        &mut diagnostics,
+                    );
+                    let range = *cursor..(*cursor + size);
+                    *cursor += size;
+                    self.diagnostics.extend(
+                        diagnostics
+                            .into_iter()
+                            .map(|diagnostic| diagnostic.offset(range.start)),
+                    );
+                    return Some(Event::Text(tag, range));
+                }
+            }
+            Frame::List { pbase, items } => {
+                if let Some(item) = items.next() {
+                    let pbase = *pbase;
+                    return Some(self.start_list_item(pbase, item));
+                }
+            }
+            Frame::ListItem { body } => {
+                if let Some((range, indent)) = body.take() {
+                    self.push_document(
+                        NodeTagName::ListBody,
+                        range.clone(),
+                        indent,
+                    );
+                    return Some(Event::Start(
+                        NodeTag::new(NodeTagName::ListBody),
+                        range,
+                    ));
+                }
+            }
+        }
+        self.stack.pop();
+        Some(Event::End(name))
+    }
+}
+
+/// Parse the leading inline statement of `text`, returns the statement tag and its size
+///
+/// Unterminated math and marks are kept as text and reported in `diagnostics` with ranges
+/// relative to `text`.
+fn parse_statement(
+    text: &str,
+    extensions: &Extensions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (NodeTag, usize) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut pos = 0;
+    let mut peeked_text = String::new();
+    let mut marks = MarkScan::default();
+    while pos < chars.len() {
+        match chars[pos] {
+            '@' if extensions.marks => {
+                match Mark::scan(&chars[pos..], &mut marks) {
+                    Ok(mark) => {
+                        if pos == 0 {
+                            let tag = NodeTag::new(NodeTagName::Extension);
+                            return (tag, mark.size);
+                        } else {
+                            break;
+                        }
+                    }
+                    Err(unclosed) => {
+                        if let Some(size) = unclosed {
+                            let start = peeked_text.len();
+                            let opening = &text[start..(start + size)];
+                            diagnostics.push(Diagnostic::warning(
+                                "unclosed-mark",
+                                format!(
+                                    "`{opening}` is not closed, it is rendered as text"
+                                ),
+                                start..(start + size),
+                            ));
+                        }
+                        peeked_text.push(chars[pos]);
+                    }
+                }
+            }
+            ch @ '*' if extensions.emphasis => {
+                if let Some(pair) = Pair::new(&chars[pos..], ch) {
+                    if pos != 0 {
+                        break;
+                    }
+                    let bsz = pair.boundaries.len();
+                    if bsz <= 2 {
+                        let emphasis = if bsz == 1 {
+                            Emphasis::Italics
+                        } else {
+                            Emphasis::Bold
+                        };
+                        let tag = NodeTag::new(NodeTagName::Emphasis(emphasis));
+                        return (tag, pair.content.len() + bsz * 2);
+                    }
+                }
+                peeked_text.push(chars[pos]);
+            }
+            ch @ '$' | ch @ '`' if ch == '`' || extensions.math => {
+                if let Some(pair) = Pair::new(&chars[pos..], ch) {
+                    if pos == 0 {
+                        let sz = pair.content.len() + pair.boundaries.len() * 2;
+                        let tag = if ch == '$' {
+                            NodeTag::new(NodeTagName::Math)
+                        } else {
+                            NodeTag::new(NodeTagName::Code)
+                                .with_attr("inlined", "")
+                        };
+                        return (tag, sz);
+                    } else {
+                        // Non-zero position `pos` indicates that we have to stop collect
+                        // `peeked_text` since we encounter another valid statement element.
+                        break;
+                    }
+                } else {
+                    let is_run_start = pos == 0 || chars[pos - 1] != ch;
+                    if ch == '$'
+                        && is_run_start
+                        && !chars[pos..]
+                            .iter()
+                            .skip_while(|&&c| c == ch)
+                            .any(|&c| c == ch)
+                    {
+                        let start = peeked_text.len();
+                        diagnostics.push(Diagnostic::warning(
+                            "unterminated-math",
+                            "math is not terminated by `$`, it is rendered as text",
+                            start..(start + 1),
+                        ));
+                    }
+                    peeked_text.push(chars[pos]);
+                }
+            }
+            '!' | '[' => {
+                // FIXME: any better way to convert char array to string with efficiency in mind?
+                let content = chars[pos..].iter().collect::<String>();
+                if let Some(link) = Link::new(content) {
+                    if pos == 0 {
+                        let tag = if link.is_image_link {
+                            NodeTag::new(NodeTagName::Image)
+                                .with_attr("src", link.uri)
+                                .with_attr("name", link.namex)
+                        } else {
+                            NodeTag::new(NodeTagName::Link)
+                                .with_attr("href", link.uri)
+                                .with_attr("name", link.namex)
+                        };
+                        return (tag, link.size);
+                    } else {
+                        break;
+                    }
+                } else {
+                    peeked_text.push(chars[pos]);
+                }
+            }
+            _ => {
+                peeked_text.push(chars[pos]);
+            }
+        }
+        pos += 1;
+    }
+
+    (NodeTag::new(NodeTagName::Text), peeked_text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let content = indoc! {r#"
+        # Title
+
+        - item *head*
+
+            body
+        "#};
+        let events = Events::new(content)
+            .map(|event| match event {
+                Event::Start(tag, range) => {
+                    format!("Start({:?}) {:?}", tag.name, &content[range])
+                }
+                Event::Text(tag, range) => {
+                    format!("Text({:?}) {:?}", tag.name, &content[range])
+                }
+                Event::End(name) => format!("End({name:?})"),
+            })
+            .collect::<Vec<String>>();
+        let wanted = vec![
+            r##"Start(Section) "# Title\n\n- item *head*\n\n    body\n""##,
+            r##"Start(Heading) "# Title\n""##,
+            r##"Text(Text) " Title\n""##,
+            "End(Heading)",
+            r##"Text(BlankLine) "\n""##,
+            r##"Start(List) "- item *head*\n\n    body\n""##,
+            r##"Start(ListItem) "- item *head*\n\n    body\n""##,
+            r##"Start(ListHead) "- item *head*\n""##,
+            r##"Text(Text) "item ""##,
+            r##"Text(Emphasis(Italics)) "*head*""##,
+            r##"Text(Text) "\n""##,
+            "End(ListHead)",
+            r##"Start(ListBody) "\n    body\n""##,
+            r##"Text(BlankLine) "\n""##,
+            r##"Start(Para) "    body\n""##,
+            r##"Text(Text) "    body\n""##,
+            "End(Para)",
+            "End(ListBody)",
+            "End(ListItem)",
+            "End(List)",
+            "End(Section)",
+        ];
+        assert_eq!(events, wanted);
+    }
+
+    #[test]
+    fn test_events_skip_meta() {
+        let content = indoc! {r#"
+        <!---
+        title = "title"
+        date = "2023-08-27 10:39:05"
+        -->
+        hello
+        "#};
+        let events = Events::new(content).collect::<Vec<Event>>();
+        let start = content.find("hello").unwrap();
+        assert_eq!(
+            events[0],
+            Event::Start(
+                NodeTag::new(NodeTagName::Section),
+                start..content.len()
+            )
+        );
+        assert_eq!(events.len(), 5);
+    }
+
+    #[test]
+    fn test_events_diagnostics() {
+        let content = "- item\n   misaligned\n";
+        let mut events = Events::new(content);
+        assert!(events.by_ref().count() > 0);
+        let start = content.find("   misaligned").unwrap();
+        let diagnostics = events.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "malformed-list");
+        assert_eq!(diagnostics[0].range, start..(content.len() - 1));
+
+        let content = "# It costs $5\n\n@math{x^2 and `$x$`\n";
+        let mut events = Events::new(content);
+        assert!(events.by_ref().count() > 0);
+        let diagnostics = events
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                (diagnostic.code, &content[diagnostic.range.clone()])
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            vec![("unterminated-math", "$"), ("unclosed-mark", "@math{")]
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! Atom and RSS feeds
+//!
+//! A [`Feed`] collects pages with [`Meta`] and writes them as an [Atom] or [RSS 2.0] document, for
+//! example
+//!
+//!     use concisemark::{feed::Feed, Page};
+//!
+//!     let page = Page::new(r#"<!---
+//!     title = "Hello"
+//!     date = "2024-01-01 00:00:00"
+//!     authors = ["Alice"]
+//!     -->
+//!
+//!     Hello, *world*!
+//!     "#);
+//!     let mut feed = Feed::new("Blog", "https://example.com/");
+//!     feed.add(&page, "https://example.com/hello.html");
+//!     let atom = feed.to_atom();
+//!     assert!(atom.contains("<title>Hello</title>"));
+//!     assert!(atom.contains("<updated>2024-01-01T00:00:00Z</updated>"));
+//!     let rss = feed.to_rss();
+//!     assert!(rss.contains("<pubDate>Mon, 1 Jan 2024 00:00:00 +0000</pubDate>"));
+//!
+//! Entries are ordered by date from newest to oldest, and by link if they have the same date, so
+//! the output does not depend on the order pages are added.
+//!
+//! [`Meta`]: crate::meta::Meta
+//! [Atom]: https://www.rfc-editor.org/rfc/rfc4287
+//! [RSS 2.0]: https://www.rssboard.org/rss-specification
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{
+    node::{self, NodeTagName},
+    options::SoftBreak,
+    render, utils, Page, PageOptions,
+};
+
+/// What is written as the content of an entry
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FeedContent {
+    /// The rendered HTML page
+    #[default]
+    Html,
+    /// A plain text excerpt of paragraphs with at most the given number of characters
+    Summary(usize),
+}
+
+/// An entry of a feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedEntry {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub date: DateTime<Utc>,
+    pub tags: Vec<String>,
+    /// Absolute url of the page, it is also used as the id of the entry
+    pub link: String,
+    /// Plain text summary, [`Meta::subtitle`](crate::meta::Meta::subtitle) or an excerpt
+    pub summary: Option<String>,
+    /// The rendered HTML page
+    pub content: Option<String>,
+}
+
+impl FeedEntry {
+    /// Create an entry from `page` which lives at `link`, returns `None` if the page has no meta
+    pub fn from_page(
+        page: &Page,
+        link: &str,
+        content: FeedContent,
+    ) -> Option<Self> {
+        let meta = page.meta.as_ref()?;
+        let (summary, content) = match content {
+            FeedContent::Html => (meta.subtitle.clone(), Some(page.render())),
+            FeedContent::Summary(size) => (Some(excerpt(page, size)), None),
+        };
+        Some(Self {
+            title: meta.title.clone(),
+            authors: meta.authors.clone().unwrap_or_default(),
+            date: meta.date,
+            tags: meta.tags.clone().unwrap_or_default(),
+            link: link.to_owned(),
+            summary,
+            content,
+        })
+    }
+}
+
+pub struct Feed {
+    pub title: String,
+    /// Absolute url of the site, it is also used as the id of the feed
+    pub link: String,
+    pub subtitle: Option<String>,
+    /// Author of the feed, used by entries without authors
+    pub author: Option<String>,
+    pub content: FeedContent,
+    entries: Vec<FeedEntry>,
+}
+
+impl Feed {
+    pub fn new<S1: AsRef<str>, S2: AsRef<str>>(title: S1, link: S2) -> Self {
+        Self {
+            title: title.as_ref().to_owned(),
+            link: link.as_ref().to_owned(),
+            subtitle: None,
+            author: None,
+            content: FeedContent::default(),
+            entries: vec![],
+        }
+    }
+
+    pub fn with_subtitle<S: AsRef<str>>(mut self, subtitle: S) -> Self {
+        self.subtitle = Some(subtitle.as_ref().to_owned());
+        self
+    }
+
+    pub fn with_author<S: AsRef<str>>(mut self, author: S) -> Self {
+        self.author = Some(author.as_ref().to_owned());
+        self
+    }
+
+    /// Set what is written as the content of entries added later
+    pub fn with_content(mut self, content: FeedContent) -> Self {
+        self.content = content;
+        self
+    }
+
+    /// Add `page` which lives at `link`, pages without meta are skipped and `false` is returned
+    pub fn add(&mut self, page: &Page, link: &str) -> bool {
+        match FeedEntry::from_page(page, link, self.content) {
+            Some(entry) => {
+                self.push(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn push(&mut self, entry: FeedEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Entries from newest to oldest, entries with the same date are ordered by link
+    pub fn entries(&self) -> Vec<&FeedEntry> {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| {
+            b.date.cmp(&a.date).then_with(|| a.link.cmp(&b.link))
+        });
+        entries
+    }
+
+    /// Date of the newest entry, or the unix epoch if there is no entry
+    pub fn updated(&self) -> DateTime<Utc> {
+        self.entries
+            .iter()
+            .map(|entry| entry.date)
+            .max()
+            .unwrap_or(DateTime::UNIX_EPOCH)
+    }
+
+    /// Write the feed as an Atom document
+    pub fn to_atom(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&element(1, "title", &self.title));
+        if let Some(subtitle) = &self.subtitle {
+            xml.push_str(&element(1, "subtitle", subtitle));
+        }
+        xml.push_str(&format!(
+            "  <link href=\"{}\"/>\n",
+            utils::escape_to_xml(&self.link)
+        ));
+        xml.push_str(&element(1, "id", &self.link));
+        xml.push_str(&element(1, "updated", &rfc3339(&self.updated())));
+        // an Atom entry must have an author, it is inherited from the feed if the entry has none,
+        // the title of the feed is the last resort
+        let author = self.author.as_deref().or_else(|| {
+            self.entries
+                .iter()
+                .any(|entry| entry.authors.is_empty())
+                .then_some(self.title.as_str())
+        });
+        if let Some(author) = author {
+            xml.push_str(&format!(
+                "  <author><name>{}</name></author>\n",
+                utils::escape_to_xml(author)
+            ));
+        }
+        for entry in self.entries() {
+            xml.push_str("  <entry>\n");
+            xml.push_str(&element(2, "title", &entry.title));
+            xml.push_str(&format!(
+                "    <link href=\"{}\"/>\n",
+                utils::escape_to_xml(&entry.link)
+            ));
+            xml.push_str(&element(2, "id", &entry.link));
+            xml.push_str(&element(2, "updated", &rfc3339(&entry.date)));
+            for author in entry.authors.iter() {
+                xml.push_str(&format!(
+                    "    <author><name>{}</name></author>\n",
+                    utils::escape_to_xml(author)
+                ));
+            }
+            for tag in entry.tags.iter() {
+                xml.push_str(&format!(
+                    "    <category term=\"{}\"/>\n",
+                    utils::escape_to_xml(tag)
+                ));
+            }
+            if let Some(summary) = &entry.summary {
+                xml.push_str(&element(2, "summary", summary));
+            }
+            if let Some(content) = &entry.content {
+                // relative links in the page are resolved against the page
+                xml.push_str(&format!(
+                    "    <content type=\"html\" xml:base=\"{}\">{}</content>\n",
+                    utils::escape_to_xml(&entry.link),
+                    utils::escape_to_xml(content)
+                ));
+            }
+            xml.push_str("  </entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    /// Write the feed as an RSS 2.0 document
+    pub fn to_rss(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<rss version=\"2.0\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+        xml.push_str("  <channel>\n");
+        xml.push_str(&element(2, "title", &self.title));
+        xml.push_str(&element(2, "link", &self.link));
+        xml.push_str(&element(
+            2,
+            "description",
+            self.subtitle.as_deref().unwrap_or(&self.title),
+        ));
+        xml.push_str(&element(
+            2,
+            "lastBuildDate",
+            &self.updated().to_rfc2822(),
+        ));
+        for entry in self.entries() {
+            xml.push_str("    <item>\n");
+            xml.push_str(&element(3, "title", &entry.title));
+            xml.push_str(&element(3, "link", &entry.link));
+            xml.push_str(&format!(
+                "      <guid isPermaLink=\"true\">{}</guid>\n",
+                utils::escape_to_xml(&entry.link)
+            ));
+            xml.push_str(&element(3, "pubDate", &entry.date.to_rfc2822()));
+            // `author` of RSS must be an email address
+            for author in entry.authors.iter() {
+                xml.push_str(&element(3, "dc:creator", author));
+            }
+            for tag in entry.tags.iter() {
+                xml.push_str(&element(3, "category", tag));
+            }
+            if let Some(description) =
+                entry.content.as_ref().or(entry.summary.as_ref())
+            {
+                xml.push_str(&element(3, "description", description));
+            }
+            xml.push_str("    </item>\n");
+        }
+        xml.push_str("  </channel>\n");
+        xml.push_str("</rss>\n");
+        xml
+    }
+}
+
+/// An element with escaped `text` on its own line
+fn element(level: usize, name: &str, text: &str) -> String {
+    format!(
+        "{}<{name}>{}</{name}>\n",
+        "  ".repeat(level),
+        utils::escape_to_xml(text)
+    )
+}
+
+fn rfc3339(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Plain text of paragraphs of `page` with at most `size` characters, `…` is appended if it is
+/// truncated
+fn excerpt(page: &Page, size: usize) -> String {
+    // marks are rendered as their text and lines of a paragraph are joined
+    let options = PageOptions {
+        soft_break: SoftBreak::Space,
+        text_urls: false,
+        ..page.options().into_owned()
+    };
+    let text = node::find_nodes_by_tag(&page.ast, NodeTagName::Para)
+        .iter()
+        .map(|para| {
+            render::text::generate(para, &page.content, &options)
+                .lines()
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<String>>()
+        .join(" ");
+    if text.chars().count() <= size {
+        return text;
+    }
+    let mut summary = text.chars().take(size).collect::<String>();
+    summary.truncate(summary.trim_end().len());
+    summary.push('…');
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn post(title: &str, date: &str, body: &str) -> Page {
+        Page::new(format!(
+            "<!---\ntitle = \"{title}\"\ndate = \"{date}\"\nauthors = [\"Alice\"]\ntags = [\"a&b\"]\n-->\n\n{body}\n"
+        ))
+    }
+
+    #[test]
+    fn test_atom() {
+        let mut feed = Feed::new("Tom & Jerry", "https://example.com/")
+            .with_subtitle("<blog>");
+        feed.add(
+            &post("Old", "2023-01-01 00:00:00", "Hi <b>"),
+            "https://example.com/old.html",
+        );
+        feed.add(
+            &post("New", "2024-01-01 12:30:00", "![logo](logo.png)"),
+            "https://example.com/new.html",
+        );
+        assert!(!feed.add(&Page::new("no meta"), "https://example.com/a"));
+
+        let wanted = indoc! {r#"
+            <?xml version="1.0" encoding="utf-8"?>
+            <feed xmlns="http://www.w3.org/2005/Atom">
+              <title>Tom &amp; Jerry</title>
+              <subtitle>&lt;blog&gt;</subtitle>
+              <link href="https://example.com/"/>
+              <id>https://example.com/</id>
+              <updated>2024-01-01T12:30:00Z</updated>
+              <entry>
+                <title>New</title>
+                <link href="https://example.com/new.html"/>
+                <id>https://example.com/new.html</id>
+                <updated>2024-01-01T12:30:00Z</updated>
+                <author><name>Alice</name></author>
+                <category term="a&amp;b"/>
+                <content type="html" xml:base="https://example.com/new.html">&lt;div&gt;&lt;p&gt;&lt;img alt=&quot;logo&quot; src=&quot;logo.png&quot;/&gt;&lt;/p&gt;&lt;/div&gt;</content>
+              </entry>
+              <entry>
+                <title>Old</title>
+                <link href="https://example.com/old.html"/>
+                <id>https://example.com/old.html</id>
+                <updated>2023-01-01T00:00:00Z</updated>
+                <author><name>Alice</name></author>
+                <category term="a&amp;b"/>
+                <content type="html" xml:base="https://example.com/old.html">&lt;div&gt;&lt;p&gt;Hi &amp;lt;b&amp;gt;&lt;/p&gt;&lt;/div&gt;</content>
+              </entry>
+            </feed>
+        "#};
+        assert_eq!(feed.to_atom(), wanted);
+    }
+
+    #[test]
+    fn test_atom_author() {
+        let page = Page::new(
+            "<!---\ntitle = \"Post\"\ndate = \"2024-01-01 00:00:00\"\n-->\n\nHello\n",
+        );
+        let mut feed = Feed::new("Blog", "https://example.com/");
+        feed.add(&page, "https://example.com/post.html");
+        let atom = feed.to_atom();
+        assert!(atom.contains("  <author><name>Blog</name></author>\n"));
+        assert!(!atom.contains("    <author>"));
+
+        let mut feed = feed.with_author("Bob");
+        feed.add(&post("Other", "2023-01-01 00:00:00", ""), "other.html");
+        let atom = feed.to_atom();
+        assert!(atom.contains("  <author><name>Bob</name></author>\n"));
+        assert!(atom.contains("    <author><name>Alice</name></author>\n"));
+    }
+
+    #[test]
+    fn test_rss() {
+        let mut feed = Feed::new("Blog", "https://example.com/")
+            .with_content(FeedContent::Summary(12));
+        // entries with the same date are ordered by link
+        for link in ["b", "a"] {
+            feed.add(
+                &post(
+                    "Post",
+                    "2024-01-01 00:00:00",
+                    "Hello, *world*!\n\n第二段",
+                ),
+                &format!("https://example.com/{link}.html"),
+            );
+        }
+        let wanted = indoc! {r#"
+            <?xml version="1.0" encoding="utf-8"?>
+            <rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+              <channel>
+                <title>Blog</title>
+                <link>https://example.com/</link>
+                <description>Blog</description>
+                <lastBuildDate>Mon, 1 Jan 2024 00:00:00 +0000</lastBuildDate>
+                <item>
+                  <title>Post</title>
+                  <link>https://example.com/a.html</link>
+                  <guid isPermaLink="true">https://example.com/a.html</guid>
+                  <pubDate>Mon, 1 Jan 2024 00:00:00 +0000</pubDate>
+                  <dc:creator>Alice</dc:creator>
+                  <category>a&amp;b</category>
+                  <description>Hello, world…</description>
+                </item>
+                <item>
+                  <title>Post</title>
+                  <link>https://example.com/b.html</link>
+                  <guid isPermaLink="true">https://example.com/b.html</guid>
+                  <pubDate>Mon, 1 Jan 2024 00:00:00 +0000</pubDate>
+                  <dc:creator>Alice</dc:creator>
+                  <category>a&amp;b</category>
+                  <description>Hello, world…</description>
+                </item>
+              </channel>
+            </rss>
+        "#};
+        assert_eq!(feed.to_rss(), wanted);
+
+        assert_eq!(
+            FeedEntry::from_page(
+                &post("Post", "2024-01-01 00:00:00", "Hello\n\n第二段"),
+                "",
+                FeedContent::Summary(100)
+            )
+            .unwrap()
+            .summary
+            .unwrap(),
+            "Hello 第二段"
+        );
+
+        // marks are rendered as their text
+        let page =
+            post("Post", "2024-01-01 00:00:00", "Press @kbd{Ctrl} and\n*go*");
+        let entry = FeedEntry::from_page(&page, "", FeedContent::Summary(100));
+        assert_eq!(entry.unwrap().summary.unwrap(), "Press Ctrl and go");
+    }
+}
@@ -0,0 +1,136 @@
+//! An opinionated ConciseMark formatter
+//!
+//! The formatter normalizes list indentation to [`List::INDENT_MARK`](crate::token::List::INDENT_MARK),
+//! heading markers to `# Heading`, separates blocks with one blank line and wraps paragraphs at
+//! the configured width without breaking math, code, links or `@mark{}` elements, for example
+//!
+//!     use concisemark::fmt::{self, FormatOptions};
+//!
+//!     let options = FormatOptions { width: Some(20) };
+//!     let content = "##Title\n- the quick brown fox jumps over the lazy dog\n";
+//!     let formatted = fmt::format(content, &options);
+//!     assert_eq!(formatted, "## Title\n\n- the quick brown\n  fox jumps over the\n  lazy dog\n");
+//!     assert!(fmt::is_formatted(&formatted, &options));
+use crate::Page;
+
+/// Options for [`format()`]
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Maximum display width of a line, east asian wide characters take two columns. Lines are
+    /// not wrapped if it is `None`.
+    pub width: Option<usize>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self { width: Some(80) }
+    }
+}
+
+/// Format ConciseMark `content`
+pub fn format<S: AsRef<str>>(content: S, options: &FormatOptions) -> String {
+    Page::new(content).render_markdown_with_width(options.width)
+}
+
+/// Check whether `content` is already formatted, that is, [`format()`] will not change it
+pub fn is_formatted<S: AsRef<str>>(
+    content: S,
+    options: &FormatOptions,
+) -> bool {
+    let content = content.as_ref();
+    format(content, options) == content
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_format_wrap() {
+        let options = FormatOptions { width: Some(24) };
+        let content = indoc! {r#"
+        Inline math $a^2 + b^2 = c^2$ and code `let a = 1;` are kept, so is @kbd{cmd + c}.
+        "#};
+        let wanted = indoc! {r#"
+        Inline math
+        $a^2 + b^2 = c^2$ and
+        code `let a = 1;` are
+        kept, so is
+        @kbd{cmd + c}.
+        "#};
+        assert_eq!(format(content, &options), wanted);
+        assert!(is_formatted(wanted, &options));
+        assert!(!is_formatted(content, &options));
+    }
+
+    #[test]
+    fn test_format_cjk() {
+        let options = FormatOptions { width: Some(16) };
+        let content = indoc! {r#"
+        这是一段很长的中文文本，包含 Rust 代码和中文标点。
+        "#};
+        let wanted = indoc! {r#"
+        这是一段很长的中
+        文文本，包含
+        Rust 代码和中文
+        标点。
+        "#};
+        let formatted = format(content, &options);
+        assert_eq!(formatted, wanted);
+        assert_eq!(Page::new(content).render(), Page::new(&formatted).render());
+
+        // unwrap joins lines without introducing spaces between east asian characters
+        let options = FormatOptions { width: None };
+        let content = format(wanted, &FormatOptions { width: Some(80) });
+        assert_eq!(
+            content,
+            "这是一段很长的中文文本，包含 Rust 代码和中文标点。\n"
+        );
+        assert_eq!(format(wanted, &options), wanted);
+    }
+
+    #[test]
+    fn test_format_blockquote() {
+        let options = FormatOptions { width: Some(10) };
+        let content = indoc! {r#"
+        > a simple blockquote
+        with very *long* body
+        "#};
+        assert_eq!(format(content, &options), content);
+    }
+
+    #[test]
+    fn test_format_idempotent() {
+        let contents = [
+            include_str!("../README.md"),
+            include_str!("../testdata/backquote_00.md"),
+            include_str!("../testdata/backquote_01.md"),
+            include_str!("../testdata/backquote_02.md"),
+            include_str!("../testdata/codeblock_00.md"),
+            include_str!("../testdata/emphasis_01.md"),
+            include_str!("../testdata/heading_00.md"),
+            include_str!("../testdata/html_char_escape_00.md"),
+            include_str!("../testdata/math_mode.md"),
+            include_str!("../testdata/para_ending_whitespace_00.md"),
+            include_str!("../testdata/para_ending_whitespace_01.md"),
+            include_str!("../testdata/zh_cn_hybrid_in_para.md"),
+            // joined lines start with a list marker
+            "-\n**\n@kbd{a}a",
+            "a\n- b\n# c\n1. d\n",
+        ];
+        for content in contents {
+            for width in [None, Some(4), Some(6), Some(10), Some(20), Some(80)]
+            {
+                let options = FormatOptions { width };
+                let formatted = format(content, &options);
+                assert_eq!(format(&formatted, &options), formatted);
+                assert_eq!(
+                    Page::new(content).render(),
+                    Page::new(&formatted).render()
+                );
+            }
+        }
+    }
+}
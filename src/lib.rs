@@ -24,21 +24,49 @@
 //! [`Page`] maintains an AST structure which you can use to hook the nodes you are
 //! interested in, please see its document for more information.
 //!
+//! ## Events
+//!
+//! If you do not need the AST, [`event::Events`] gives you a pull parser which yields a flat
+//! stream of start, text and end events.
+//!
+//! ## Options
+//!
+//! Syntax extensions, heading ids, sanitization, soft breaks, image paths, the LaTeX template
+//! and math output are configured by [`PageOptions`], see [`Page::new_with`]. Untrusted input
+//! such as user comments should be rendered with
+//! [`SanitizePolicy::Safe`](options::SanitizePolicy::Safe).
+//!
+//! ## Diagnostics
+//!
+//! Problems found in the markdown content are collected as [`diagnostic::Diagnostic`]s, see
+//! [`Page::diagnostics`]. They are not fatal unless [`PageOptions::strict`] is set, in which case
+//! [`Page::try_new_with`] returns a [`diagnostic::ParseError`] for problems found when parsing.
+//!
+pub mod config;
+pub mod diagnostic;
+pub mod docx;
+pub mod epub;
+pub mod event;
+pub mod feed;
+pub mod fmt;
 pub mod meta;
 pub mod node;
+pub mod options;
 mod parser;
 mod render;
+pub mod search;
+pub mod site;
 pub mod token;
 pub mod utils;
 
+use std::{borrow::Cow, fs};
+
+use diagnostic::{Diagnostic, ParseError};
 use meta::Meta;
-use node::Node;
+use node::{Node, NodeTagName};
+pub use options::PageOptions;
 use parser::Parser;
 
-/// A placehodler for future usage
-#[derive(Debug)]
-pub struct PageOptions {}
-
 /// A markdown page
 pub struct Page {
     /// Meta information for the page, such as author, tags ...
@@ -48,27 +76,127 @@ pub struct Page {
     /// The markdown file content (with `meta` stripped). `ast` does not store any text but only node range,
     /// and content is necessary to retrive node text with `ast` information.
     pub content: String,
-    /// Page options, a placehodler for future usage
+    /// Page options, the default options are used if it is `None`
     pub options: Option<PageOptions>,
+    /// Diagnostics found when parsing
+    parse_diagnostics: Vec<Diagnostic>,
 }
 
 impl Page {
     /// Create a new markdown page from `content`
     pub fn new<S: AsRef<str>>(content: S) -> Self {
-        let (meta, ast, content) = Parser::new(content).parse();
+        let (meta, ast, content, parse_diagnostics) =
+            Parser::new(content).parse();
         Self {
             meta,
             ast,
             content,
             options: None,
+            parse_diagnostics,
+        }
+    }
+
+    /// Create a new markdown page from `content` with `options`, see [`PageOptions`]
+    ///
+    ///     use concisemark::{Page, PageOptions};
+    ///
+    ///     let options = PageOptions {
+    ///         heading_ids: true,
+    ///         ..Default::default()
+    ///     };
+    ///     let page = Page::new_with("# Hello World", options);
+    ///     assert!(page.render().contains(r#"<h1 id="hello-world">"#));
+    pub fn new_with<S: AsRef<str>>(content: S, options: PageOptions) -> Self {
+        let (meta, ast, content, parse_diagnostics) =
+            Parser::new(content).with_options(&options).parse();
+        Self {
+            meta,
+            ast,
+            content,
+            options: Some(options),
+            parse_diagnostics,
+        }
+    }
+
+    /// Problems found in the page ordered by their positions
+    ///
+    /// Besides problems found when parsing such as a malformed list, the (possibly transformed)
+    /// AST is checked for problems which affect the rendered output, such as unsupported marks
+    /// and images which do not exist.
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("- item\n   misaligned line\n");
+    ///     let diagnostics = page.diagnostics();
+    ///     assert_eq!(diagnostics[0].code, "malformed-list");
+    ///     assert_eq!(diagnostics[0].location(&page.content), (2, 1));
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.parse_diagnostics.clone();
+        diagnostics.extend(diagnostic::check(
+            &self.ast,
+            &self.content,
+            &self.options(),
+        ));
+        diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+        diagnostics
+    }
+
+    /// Create a new markdown page from `content` with `options`
+    ///
+    /// Parsing never fails unless [`PageOptions::strict`] is set, in which case any problem found
+    /// when parsing, such as unterminated math, an unclosed mark, a misaligned list or an invalid
+    /// meta, is returned as a [`ParseError`].
+    ///
+    ///     use concisemark::{Page, PageOptions};
+    ///
+    ///     let options = PageOptions {
+    ///         strict: true,
+    ///         ..Default::default()
+    ///     };
+    ///     assert!(Page::try_new_with("It costs $5", options.clone()).is_err());
+    ///     assert!(Page::try_new_with("It costs $5$", options).is_ok());
+    ///     // lenient mode renders the malformed input as text
+    ///     assert!(Page::try_new_with("It costs $5", Default::default()).is_ok());
+    pub fn try_new_with<S: AsRef<str>>(
+        content: S,
+        options: PageOptions,
+    ) -> Result<Self, ParseError> {
+        let strict = options.strict;
+        let page = Self::new_with(content, options);
+        if strict && !page.parse_diagnostics.is_empty() {
+            return Err(ParseError {
+                diagnostics: page.parse_diagnostics,
+            });
         }
+        Ok(page)
     }
 
+    /// Set options used when rendering the page
+    ///
+    /// The page is not parsed again, so changes made by [`Page::transform`] are kept and options
+    /// which affect parsing, such as [`PageOptions::extensions`] and [`PageOptions::heading_ids`],
+    /// have no effect; use [`Page::new_with`] for them.
+    ///
+    ///     use concisemark::{Page, PageOptions};
+    ///
+    ///     let options = PageOptions {
+    ///         pretty_html: true,
+    ///         ..Default::default()
+    ///     };
+    ///     let page = Page::new("# Title").with_options(options);
+    ///     assert_eq!(page.render(), "<div>\n  <h1>Title</h1>\n</div>\n");
     pub fn with_options(mut self, options: PageOptions) -> Self {
         self.options = Some(options);
         self
     }
 
+    fn options(&self) -> Cow<'_, PageOptions> {
+        match &self.options {
+            Some(options) => Cow::Borrowed(options),
+            None => Cow::Owned(PageOptions::default()),
+        }
+    }
+
     /// Render markdown into HTML page
     ///
     ///     use concisemark::Page;
@@ -162,7 +290,18 @@ impl Page {
     ///     cmd.arg(&texfile);
     ///     _ = cmd.output();
     pub fn render_latex(&self) -> String {
-        let mut page = include_str!("../assets/setup.tex").to_owned();
+        let options = self.options();
+        let setup = include_str!("../assets/setup.tex");
+        let mut page = match &options.latex_template {
+            Some(path) => fs::read_to_string(path).unwrap_or_else(|e| {
+                log::warn!(
+                    "failed to read latex template {}: {e}, use the default one",
+                    path.display()
+                );
+                setup.to_owned()
+            }),
+            None => setup.to_owned(),
+        };
         let mut document = render::latex::Cmd::new("document").enclosed();
         if let Some(meta) = &self.meta {
             let title =
@@ -179,12 +318,165 @@ impl Page {
             let maketitle = render::latex::Cmd::new("maketitle");
             document.append_cmd(&maketitle);
         }
-        document
-            .append(render::latex::generate(&self.ast, self.content.as_str()));
+        document.append(render::latex::generate(
+            &self.ast,
+            &self.content,
+            &options,
+        ));
         page.push_str(&document.to_string());
         page
     }
 
+    /// Render markdown into Typst source
+    ///
+    /// Title, subtitle, authors and date of [`Meta`] are put into the document metadata and a
+    /// title block. Common TeX math is translated into Typst math, math using unsupported commands
+    /// is kept as a raw LaTeX block.
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("# Intro\n\nSee *this* $\\frac{1}{2}$");
+    ///     assert_eq!(page.render_typst(), "= Intro\n\nSee #emph[this] $frac(1, 2)$\n");
+    pub fn render_typst(&self) -> String {
+        let mut page = String::new();
+        if let Some(meta) = &self.meta {
+            page.push_str(&render::typst::preamble(meta));
+        }
+        page.push_str(&render::typst::generate(
+            &self.ast,
+            &self.content,
+            &self.options(),
+        ));
+        page
+    }
+
+    /// Render markdown into a man page (roff source with `man(7)` macros)
+    ///
+    /// [`Meta::title`] in `name(section)` form such as `ls(1)` becomes the `.TH` line, and
+    /// [`Meta::subtitle`] becomes the `NAME` section. Level 1 and 2 headings are sections and
+    /// subsections.
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("# OPTIONS\n\n- `--help` prints *help*");
+    ///     assert_eq!(
+    ///         page.render_man(),
+    ///         ".SH \"OPTIONS\"\n.IP \\(bu 2\n\\fB\\-\\-help\\fR prints \\fIhelp\\fR\n"
+    ///     );
+    pub fn render_man(&self) -> String {
+        let mut page = String::new();
+        if let Some(meta) = &self.meta {
+            page.push_str(&render::man::preamble(meta));
+        }
+        page.push_str(&render::man::generate(
+            &self.ast,
+            &self.content,
+            &self.options(),
+        ));
+        page
+    }
+
+    /// Render markdown into LaTeX Beamer slides
+    ///
+    /// Level-1 and level-2 headings start new frames (level-1 headings start sections as well),
+    /// [`Meta`] is used for the title frame and `@note{...}` marks become speaker notes.
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("## Goals\n\nShip it @note{mention the deadline}");
+    ///     let beamer = page.render_beamer();
+    ///     assert!(beamer.contains("\\begin{frame}{Goals}\nShip it \n\\note{mention the deadline}\n\\end{frame}"));
+    pub fn render_beamer(&self) -> String {
+        render::slides::beamer(
+            &self.ast,
+            &self.content,
+            self.meta.as_ref(),
+            &self.options(),
+        )
+    }
+
+    /// Render markdown into a self-contained slide deck in HTML
+    ///
+    /// Frames are split like [`Page::render_beamer`] and written with the markup of
+    /// [reveal.js](https://revealjs.com), the page bundles the style and a small script to
+    /// navigate slides with arrow keys and to toggle speaker notes with `n`.
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("## Goals\n\nShip it @note{mention the deadline}");
+    ///     let html = page.render_slides();
+    ///     assert!(html.contains("<aside class=\"notes\">\n<p>mention the deadline</p>\n</aside>"));
+    pub fn render_slides(&self) -> String {
+        render::slides::html(
+            &self.ast,
+            &self.content,
+            self.meta.as_ref(),
+            &self.options(),
+        )
+    }
+
+    /// Render the page back into canonical ConciseMark source
+    ///
+    /// The AST (including the changes made by [`Page::transform`]) and meta are serialized, and
+    /// parsing the output again yields an equivalent AST.
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("#Title\n- item\n  continued");
+    ///     assert_eq!(page.render_markdown(), "# Title\n\n- item\n  continued\n");
+    pub fn render_markdown(&self) -> String {
+        self.render_markdown_with_width(None)
+    }
+
+    /// Render the page back into ConciseMark source with paragraphs wrapped to `width` columns
+    pub(crate) fn render_markdown_with_width(
+        &self,
+        width: Option<usize>,
+    ) -> String {
+        let mut page = String::new();
+        if let Some(meta) = &self.meta {
+            page.push_str(&meta.to_comment());
+            page.push('\n');
+        }
+        page.push_str(&render::markdown::generate(
+            &self.ast,
+            self.content.as_str(),
+            width,
+        ));
+        page
+    }
+
+    /// Render the page into plain text
+    ///
+    /// All markups are stripped while paragraphs and lists are kept, link urls are dropped unless
+    /// [`PageOptions::text_urls`] is on, marks are rendered as their text and math is kept as
+    /// raw TeX.
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("# Title\n\nPress @kbd{cmd+c} to copy *this* [link](https://a.b)\n\n- $a^2$\n");
+    ///     assert_eq!(page.render_text(), "Title\n\nPress ⌘+c to copy this link\n\n- a^2\n");
+    pub fn render_text(&self) -> String {
+        render::text::generate(&self.ast, &self.content, &self.options())
+    }
+
+    /// Render the page for terminals
+    ///
+    /// Paragraphs and list items are wrapped to fit in `width` columns (east asian characters
+    /// take two columns), headings, emphasis, code and math are styled by ANSI SGR codes, links
+    /// are OSC 8 hyperlinks and code blocks are boxed.
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("Some *text*\n\n    code");
+    ///     assert_eq!(
+    ///         page.render_ansi(80),
+    ///         "Some \x1b[3mtext\x1b[23m\n\n┌──────┐\n│ code │\n└──────┘\n"
+    ///     );
+    pub fn render_ansi(&self, width: usize) -> String {
+        render::ansi::generate(&self.ast, &self.content, &self.options(), width)
+    }
+
     /// Render markdown into HTML page with hook
     ///
     /// If the hook returns None, then the default rendering function will be used or else
@@ -193,7 +485,131 @@ impl Page {
     where
         F: Fn(&Node) -> Option<String>,
     {
-        render::html::generate(&self.ast, self.content.as_str(), Some(hook))
+        render::html::generate(
+            &self.ast,
+            &self.content,
+            &self.options(),
+            Some(hook),
+        )
+    }
+
+    /// Render markdown into a complete HTML5 document
+    ///
+    /// The `<head>` is generated from [`Meta`]: the title (or the first heading if there is no
+    /// meta), authors, tags as keywords, the subtitle as description, and the same information
+    /// as [OpenGraph](https://ogp.me) tags. Stylesheet and scripts are configured by
+    /// [`PageOptions::document`](options::DocumentOptions).
+    ///
+    ///     use concisemark::Page;
+    ///
+    ///     let page = Page::new("<!---\ntitle = \"Hello\"\ndate = \"2024-03-05 00:00:00\"\ntags = [\"a\", \"b\"]\n-->\nWorld");
+    ///     let html = page.render_html_document();
+    ///     assert!(html.starts_with("<!DOCTYPE html>"));
+    ///     assert!(html.contains("<title>Hello</title>"));
+    ///     assert!(html.contains("<meta name=\"keywords\" content=\"a, b\">"));
+    ///     assert!(html.contains("<meta property=\"og:title\" content=\"Hello\">"));
+    pub fn render_html_document(&self) -> String {
+        let options = self.options();
+        let document = &options.document;
+        let mut head = String::new();
+        let mut push_meta = |attr: &str, name: &str, value: &str| {
+            head.push_str(&format!(
+                "<meta {attr}=\"{name}\" content=\"{}\">\n",
+                utils::escape_html_double_quote(value)
+            ));
+        };
+        let title = match &self.meta {
+            Some(meta) => {
+                let authors = meta.authors.clone().unwrap_or_default();
+                let tags = meta.tags.clone().unwrap_or_default();
+                if !authors.is_empty() {
+                    push_meta("name", "author", &authors.join(", "));
+                }
+                if !tags.is_empty() {
+                    push_meta("name", "keywords", &tags.join(", "));
+                }
+                if let Some(subtitle) = &meta.subtitle {
+                    push_meta("name", "description", subtitle);
+                }
+                push_meta("property", "og:title", &meta.title);
+                push_meta("property", "og:type", "article");
+                if let Some(subtitle) = &meta.subtitle {
+                    push_meta("property", "og:description", subtitle);
+                }
+                push_meta(
+                    "property",
+                    "article:published_time",
+                    &meta.date.to_rfc3339(),
+                );
+                for author in &authors {
+                    push_meta("property", "article:author", author);
+                }
+                for tag in &tags {
+                    push_meta("property", "article:tag", tag);
+                }
+                meta.title.clone()
+            }
+            None => node::find_nodes_by_tag(&self.ast, NodeTagName::Heading)
+                .first()
+                .map(|heading| node::inline_text(heading, &self.content))
+                .unwrap_or_default(),
+        };
+
+        let stylesheet = include_str!("../assets/serve/style.css");
+        let stylesheet = match &document.stylesheet {
+            _ if !document.theme => None,
+            Some(path) => Some(fs::read_to_string(path).unwrap_or_else(|e| {
+                log::warn!(
+                    "failed to read stylesheet {}: {e}, use the default one",
+                    path.display()
+                );
+                stylesheet.to_owned()
+            })),
+            None => Some(stylesheet.to_owned()),
+        };
+        if let Some(stylesheet) = stylesheet {
+            head.push_str(&format!("<style>\n{stylesheet}</style>\n"));
+        }
+        let mut scripts = String::new();
+        if !node::find_nodes_by_tag(&self.ast, NodeTagName::Math).is_empty() {
+            match &document.math_script {
+                Some(src) => scripts.push_str(&format!(
+                    "<script defer src=\"{}\"></script>\n",
+                    utils::escape_html_double_quote(src)
+                )),
+                None => scripts.push_str(&format!(
+                    "<script>\n{}</script>\n",
+                    include_str!("../assets/serve/math.js")
+                )),
+            }
+        }
+        if let Some(src) = &document.highlight_script {
+            scripts.push_str(&format!(
+                "<script src=\"{}\"></script>\n",
+                utils::escape_html_double_quote(src)
+            ));
+            scripts.push_str(concat!(
+                "<script>\n",
+                "if (window.hljs) hljs.highlightAll();\n",
+                "</script>\n"
+            ));
+        }
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{}</title>
+{head}</head>
+<body>
+{}
+{scripts}</body>
+</html>
+"#,
+            utils::escape_to_html(title.trim()),
+            self.render()
+        )
     }
 
     /// Modify markdown AST node with hook.
@@ -244,7 +660,7 @@ mod tests {
     use markup5ever_rcdom::{Handle, NodeData, RcDom};
     use node::NodeTagName;
 
-    use crate::*;
+    use crate::{diagnostic::Severity, *};
 
     fn is_self_closing_tag(tag: &str) -> bool {
         let self_closing_tag_list = vec![
@@ -255,7 +671,25 @@ mod tests {
             "input", "keygen", "link", "meta", "param", "source", "track",
             "wbr",
         ];
-        self_closing_tag_list.iter().any(|&i| i == tag)
+        self_closing_tag_list.contains(&tag)
+    }
+
+    /// Parse `html` as the content of `body`
+    fn parse_html(html: &str) -> RcDom {
+        let parser = parse_fragment(
+            RcDom::default(),
+            ParseOpts::default(),
+            QualName::new(None, ns!(html), local_name!("body")),
+            vec![],
+        );
+        parser.one(html)
+    }
+
+    /// Assert that `html` is parsed without errors by an HTML5 parser, the parser reports
+    /// mis-nested tags and blocks in paragraphs for example
+    fn assert_valid_html(html: &str) {
+        let dom = parse_html(html);
+        assert!(dom.errors.is_empty(), "{:?}\n{html}", dom.errors);
     }
 
     fn get_html_outline(dirty_html: &str) -> String {
@@ -287,13 +721,7 @@ mod tests {
             outline
         }
 
-        let parser = parse_fragment(
-            RcDom::default(),
-            ParseOpts::default(),
-            QualName::new(None, ns!(html), local_name!("body")),
-            vec![],
-        );
-        let mut dom = parser.one(dirty_html);
+        let mut dom = parse_html(dirty_html);
         let html = dom.get_document();
         let body = &html.children.borrow()[0];
         let mut outline = String::new();
@@ -350,14 +778,18 @@ mod tests {
             <div>
               <ul>
                 <li>
-                  <a>
-                  </a>
+                  <p>
+                    <a>
+                    </a>
+                  </p>
                   <p>
                   </p>
                 </li>
                 <li>
-                  <a>
-                  </a>
+                  <p>
+                    <a>
+                    </a>
+                  </p>
                 </li>
               </ul>
             </div>
@@ -398,7 +830,7 @@ example
         let page = Page::new(content);
         let html = page.render();
         let wanted_html = indoc! {r#"
-        <div><p>This is a sentence with emphasis <em> itaclics </em>and <strong> bold </strong>. </p></div>
+        <div><p>This is a sentence with emphasis <em>itaclics</em> and <strong>bold</strong>.</p></div>
         "#};
         assert_eq!(html, wanted_html.trim());
 
@@ -412,7 +844,7 @@ example
         let content = include_str!("../testdata/backquote_00.md");
         let page = Page::new(content);
         let html = page.render();
-        let wanted_html = "<div><blockquote><p>a simple blockquote with very long body really long body ... </p></blockquote></div>";
+        let wanted_html = "<div><blockquote><p>a simple blockquote with very long body really long body ...</p></blockquote></div>";
         assert_eq!(html, wanted_html);
     }
 
@@ -421,14 +853,14 @@ example
         let content = include_str!("../testdata/backquote_01.md");
         let page = Page::new(content);
         let html = page.render();
-        let wanted_html = "<div><ul><li>title <blockquote><p>a simple line <br/>abc <strong> line </strong> <em> line </em>test </p></blockquote></li></ul></div>";
+        let wanted_html = "<div><ul><li><p>title</p><blockquote><p>a simple line<br/>abc <strong>line</strong> <em>line</em> test</p></blockquote></li></ul></div>";
         assert_eq!(html, wanted_html);
     }
 
     #[test]
     fn test_backquote_02() {
         let content = include_str!("../testdata/backquote_02.md");
-        let wanted_html = "<div><blockquote><p>a simple line <br/>line test </p></blockquote></div>";
+        let wanted_html = "<div><blockquote><p>a simple line<br/>line test</p></blockquote></div>";
         let page = Page::new(content);
         let html = page.render();
         assert_eq!(html, wanted_html.trim());
@@ -444,7 +876,7 @@ example
         test
         "#};
         let wanted_html = indoc! {r#"
-        <div><blockquote><p>a simple line <br/>abc <strong> line </strong> <em> line </em>test </p></blockquote></div>
+        <div><blockquote><p>a simple line<br/>abc <strong>line</strong> <em>line</em> test</p></blockquote></div>
         "#};
         let page = Page::new(content);
         let html = page.render();
@@ -468,6 +900,19 @@ example
         assert_eq!(html, wanted_html.trim());
     }
 
+    #[test]
+    fn test_backquote_trailing_marker() {
+        // a trailing `>` line breaks nothing whether the text ends with a newline or not
+        for content in ["> a\n>\n", "> a\n>", "a\n>\n", "a\n>"] {
+            assert!(!Page::new(content).render().contains("<br/>"));
+        }
+        let html = Page::new("> a\n>\n> *b*\n").render();
+        assert_eq!(
+            html,
+            "<div><blockquote><p>a<br/><em>b</em></p></blockquote></div>"
+        );
+    }
+
     #[test]
     fn test_para_ending_whitesapce_00() {
         // require space between `2008 年` and `8 月 8 日`
@@ -533,6 +978,999 @@ example
         );
     }
 
+    /// Get a signature of AST which ignores ranges, blank lines and whitespaces
+    fn ast_signature(page: &Page) -> String {
+        fn walker(node: &Node, content: &str, indent: usize) -> String {
+            let nodedata = node.data.borrow();
+            let text = content[nodedata.range.start..nodedata.range.end]
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ");
+            match nodedata.tag.name {
+                NodeTagName::BlankLine => return "".to_owned(),
+                NodeTagName::Text if text.is_empty() => return "".to_owned(),
+                _ => {}
+            }
+            let mut attrs = nodedata.tag.attrs.iter().collect::<Vec<_>>();
+            attrs.sort();
+            let mut signature = format!(
+                "{}{:?} {attrs:?}",
+                " ".repeat(indent),
+                nodedata.tag.name
+            );
+            if nodedata.children.is_empty() {
+                signature += &format!(" = {text}");
+            }
+            signature.push('\n');
+            for child in node.children() {
+                signature += &walker(&child, content, indent + 2);
+            }
+            signature
+        }
+        walker(&page.ast, &page.content, 0)
+    }
+
+    #[test]
+    fn test_render_markdown_roundtrip() {
+        let contents = [
+            include_str!("../README.md"),
+            include_str!("../testdata/backquote_00.md"),
+            include_str!("../testdata/backquote_01.md"),
+            include_str!("../testdata/backquote_02.md"),
+            include_str!("../testdata/codeblock_00.md"),
+            include_str!("../testdata/emphasis_01.md"),
+            include_str!("../testdata/heading_00.md"),
+            include_str!("../testdata/html_char_escape_00.md"),
+            include_str!("../testdata/math_mode.md"),
+            include_str!("../testdata/para_ending_whitespace_00.md"),
+            include_str!("../testdata/para_ending_whitespace_01.md"),
+            include_str!("../testdata/zh_cn_hybrid_in_para.md"),
+            // paragraphs which would start a list or heading without indentation
+            "  - item\n",
+            "\t# **\n",
+            "- a\n\n\t- b\n",
+            // an empty list item
+            "- \n",
+        ];
+        for content in contents {
+            let page = Page::new(content);
+            let markdown = page.render_markdown();
+            let reparsed = Page::new(&markdown);
+            assert_eq!(ast_signature(&page), ast_signature(&reparsed));
+            assert_eq!(markdown, reparsed.render_markdown());
+            assert_eq!(page.render(), reparsed.render());
+        }
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let content = indoc! {r#"
+        <!---
+        title = "title"
+        date = "2023-08-27 10:39:05"
+        tags = ["demo"]
+        -->
+        ###title
+        some text
+        continued with [link](https://example.com)
+
+        - item
+          continued
+
+            body
+            - nested
+
+
+        - item2
+
+                code
+        "#};
+        let page = Page::new(content);
+        let hook = |node: &Node| -> Result<(), ()> {
+            let mut nodedata = node.data.borrow_mut();
+            if nodedata.tag.name == NodeTagName::Link {
+                nodedata.tag.attrs.insert(
+                    "href".to_owned(),
+                    "https://example.org".to_owned(),
+                );
+            }
+            Ok(())
+        };
+        page.transform(hook);
+        let wanted = indoc! {r#"
+        <!---
+        date = "2023-08-27 10:39:05"
+        title = "title"
+        tags = ["demo"]
+        -->
+
+        ### title
+
+        some text
+        continued with [link](https://example.org)
+
+        - item
+          continued
+
+            body
+            - nested
+
+        - item2
+
+                code
+        "#};
+        assert_eq!(page.render_markdown(), wanted);
+    }
+
+    #[test]
+    fn test_render_text() {
+        let content = indoc! {r#"
+        # Title *here*
+
+        Some **bold** text with `code` and $e^{i\pi}$,
+        see [docs](https://example.com) and ![logo](logo.png).
+        中文
+        换行 @emoji{smile} @char{#}
+
+        > quoted
+        > text
+
+        - item
+          continued
+
+            body
+
+                code
+            - nested
+
+
+        - item2
+        "#};
+        let wanted = indoc! {r#"
+        Title here
+
+        Some bold text with code and e^{i\pi}, see docs and logo. 中文换行 😄 #
+
+        > quoted text
+
+        - item continued
+
+          body
+
+              code
+
+          - nested
+
+        - item2
+        "#};
+        let page = Page::new(content);
+        assert_eq!(page.render_text(), wanted);
+
+        let options = PageOptions {
+            text_urls: true,
+            soft_break: options::SoftBreak::Newline,
+            ..Default::default()
+        };
+        let text = Page::new_with(
+            "[docs](https://a.b) [https://a.b](https://a.b)\nnext",
+            options,
+        )
+        .render_text();
+        assert_eq!(text, "docs (https://a.b) https://a.b\nnext\n");
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let content = indoc! {r#"
+        # Title
+
+        Some **bold** text with `code`, $a^2$ and a [link](https://a.b)
+        that wraps. 中文中文中文
+        中文中文中文
+
+        > quoted
+
+        - item with a long line
+            - nested
+
+                    code
+        "#};
+        let wanted = concat!(
+            "\x1b[1;4;35mTitle\x1b[22;24;39m\n",
+            "\n",
+            "Some \x1b[1mbold\x1b[22m text with\n",
+            "\x1b[33mcode\x1b[39m, \x1b[32ma^2\x1b[39m and a \x1b]8;;https://a.b\x1b\\\x1b[4;34mlink\x1b[24;39m\x1b]8;;\x1b\\\n",
+            "that wraps. 中文中文\n",
+            "中文中文中文中文\n",
+            "\n",
+            "\x1b[2m│\x1b[22m quoted\n",
+            "\n",
+            "• item with a long\n",
+            "  line\n",
+            "\n",
+            "  ◦ nested\n",
+            "\n",
+            "    ┌──────┐\n",
+            "    │ code │\n",
+            "    └──────┘\n",
+        );
+        let page = Page::new(content);
+        assert_eq!(page.render_ansi(20), wanted);
+
+        // control characters in the content can not inject escape sequences
+        let page =
+            Page::new("a\x1b[31mb *c\x1b[0m* `d\x07`\n\n    e\x1b]0;x\x07\n");
+        let ansi = page.render_ansi(80);
+        assert!(
+            ansi.starts_with("a[31mb \x1b[3mc[0m\x1b[23m \x1b[33md\x1b[39m\n")
+        );
+        assert!(ansi.contains("│ e]0;x │"));
+        assert!(!ansi.contains('\x07'));
+
+        // control characters can not escape the hyperlink
+        let page = Page::new("[a\x1b]8;;b](https://a.b/\x1b\\\x07x)\n");
+        assert_eq!(
+            page.render_ansi(80),
+            "\x1b]8;;https://a.b/\\x\x1b\\\x1b[4;34ma]8;;b\x1b[24;39m\x1b]8;;\x1b\\\n"
+        );
+    }
+
+    #[test]
+    fn test_render_typst() {
+        let content = indoc! {r#"
+        <!---
+        title = "A \"quoted\" title"
+        subtitle = "Sub"
+        date = "2024-03-05 00:00:00"
+        authors = ["Alice", "Bob"]
+        -->
+        # Intro
+
+        Some **bold** and *italic* text with `code` and #hash,
+        see [docs](https://example.com) and @kbd{cmd+c}.
+        - not a list $x^{2}$ and $\begin{cases}a\end{cases}$
+
+        > quoted
+
+        - item
+
+                fn main() {}
+            - nested
+
+        $$\sum_{i=1}^n i$$
+        "#};
+        let wanted = indoc! {r#"
+        #set document(title: "A \"quoted\" title", author: ("Alice","Bob",), date: datetime(year: 2024, month: 3, day: 5))
+        #align(center)[
+          #text(size: 2em, weight: "bold")[A "quoted" title]
+
+          #text(size: 1.2em)[Sub]
+
+          Alice, Bob
+
+          2024-03-05
+        ]
+
+        = Intro
+
+        Some #strong[bold] and #emph[italic] text with `code` and \#hash, see #link("https://example.com")[docs] and ⌘+c. \- not a list $x^2$ and #raw(lang: "latex", "\\begin{cases}a\\end{cases}")
+
+        #quote(block: true)[quoted]
+
+        - item
+
+          ```
+          fn main() {}
+          ```
+
+          - nested
+
+        $ sum_(i = 1)^n i $
+        "#};
+        let page = Page::new(content);
+        let hook = |node: &Node| -> Result<(), ()> {
+            let mut nodedata = node.data.borrow_mut();
+            if nodedata.tag.name == NodeTagName::Code {
+                nodedata
+                    .tag
+                    .attrs
+                    .insert("lang".to_owned(), "rust".to_owned());
+            }
+            Ok(())
+        };
+        assert_eq!(page.render_typst(), wanted);
+        page.transform(hook);
+        assert!(page.render_typst().contains("```rust\n  fn main() {}"));
+    }
+
+    #[test]
+    fn test_render_man() {
+        let content = indoc! {r#"
+        <!---
+        title = "cm(1)"
+        subtitle = "render ConciseMark"
+        date = "2024-03-05 00:00:00"
+        -->
+        # SYNOPSIS
+
+        **cm** [*options*] file
+        .dot line and C:\path
+
+        ## Examples
+
+        > quoted
+
+        - item
+
+                cm -o out.html
+                .hidden
+            - nested
+
+        ### Details
+        "#};
+        let wanted = indoc! {r#"
+        .TH "CM" "1" "2024\-03\-05"
+        .SH NAME
+        cm \- render ConciseMark
+        .SH "SYNOPSIS"
+        .PP
+        \fBcm\fR [\fIoptions\fR] file
+        \&.dot line and C:\epath
+        .SS "Examples"
+        .RS
+        .PP
+        quoted
+        .RE
+        .IP \(bu 2
+        item
+        .RS 2
+        .PP
+        .EX
+        cm \-o out.html
+        \&.hidden
+        .EE
+        .IP \(bu 2
+        nested
+        .RE
+        .PP
+        \fBDetails\fR
+        "#};
+        let options = PageOptions {
+            soft_break: options::SoftBreak::Newline,
+            ..Default::default()
+        };
+        assert_eq!(Page::new_with(content, options).render_man(), wanted);
+    }
+
+    const SLIDES: &str = indoc! {r#"
+    <!---
+    title = "Deck"
+    authors = ["Ann"]
+    date = "2024-03-05 00:00:00"
+    -->
+    Intro @note{hello}
+
+    # Part
+
+    ## Code
+
+        let a = 1;
+
+    ### Detail
+
+    Text
+
+    @note{only note}
+    "#};
+
+    #[test]
+    fn test_render_beamer() {
+        let page = Page::new(SLIDES);
+        let beamer = page.render_beamer();
+        assert!(beamer.starts_with("\\documentclass"));
+        let wanted = indoc! {r#"
+        \begin{document}
+        \title{Deck}
+        \author{Ann}
+        \date{2024-03-05}
+        \begin{frame}
+        \titlepage
+        \end{frame}
+        \begin{frame}
+        Intro 
+        \note{hello}
+        \end{frame}
+        \section{Part}
+        \begin{frame}{Part}
+
+        \end{frame}
+        \begin{frame}[fragile]{Code}
+        \begin{lstlisting}[style=verb]
+        let a = 1;
+        \end{lstlisting}
+        \textbf{Detail}
+
+        Text
+
+        \note{only note}
+        \end{frame}
+        \end{document}
+        "#};
+        assert!(beamer.ends_with(wanted), "{beamer}");
+    }
+
+    #[test]
+    fn test_render_slides() {
+        let page = Page::new(SLIDES);
+        let html = page.render_slides();
+        assert!(html.contains("<title>Deck</title>"));
+        let wanted = indoc! {r#"
+        <div class="slides">
+        <section class="title-slide">
+        <h1>Deck</h1>
+        <p class="author">Ann</p>
+        <p class="date">2024-03-05</p>
+        </section>
+        <section>
+        <p>Intro </p>
+        <aside class="notes">
+        <p>hello</p>
+        </aside>
+        </section>
+        <section data-level="1">
+        <h1>Part</h1>
+        </section>
+        <section data-level="2">
+        <h2>Code</h2>
+        <pre><code>let a = 1;</code></pre>
+        <h3>Detail</h3>
+        <p>Text</p>
+        <aside class="notes">
+        <p>only note</p>
+        </aside>
+        </section>
+        </div>
+        "#};
+        assert!(html.contains(wanted), "{html}");
+        // notes are hidden outside of slides
+        assert!(!page.render().contains("hello"));
+    }
+
+    #[test]
+    fn test_render_html_document() {
+        let content = indoc! {r#"
+        <!---
+        title = "Tom & Jerry"
+        subtitle = "A \"classic\""
+        date = "2024-03-05 00:00:00"
+        authors = ["Tom", "Jerry"]
+        tags = ["cartoon"]
+        -->
+        Some $a^2$ math
+        "#};
+        let page = Page::new(content);
+        let html = page.render_html_document();
+        let wanted = indoc! {r#"
+        <title>Tom &amp; Jerry</title>
+        <meta name="author" content="Tom, Jerry">
+        <meta name="keywords" content="cartoon">
+        <meta name="description" content="A &quot;classic&quot;">
+        <meta property="og:title" content="Tom &amp; Jerry">
+        <meta property="og:type" content="article">
+        <meta property="og:description" content="A &quot;classic&quot;">
+        <meta property="article:published_time" content="2024-03-05T00:00:00+00:00">
+        <meta property="article:author" content="Tom">
+        <meta property="article:author" content="Jerry">
+        <meta property="article:tag" content="cartoon">
+        <style>
+        "#};
+        assert!(html.contains(wanted), "{html}");
+        assert!(html.contains(&page.render()));
+        assert!(html.contains("MathML"));
+        assert!(html.ends_with("</script>\n</body>\n</html>\n"));
+
+        let options = PageOptions {
+            document: options::DocumentOptions {
+                theme: false,
+                math_script: Some("https://example.com/math.js".to_owned()),
+                highlight_script: Some("hl.js".to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let page = Page::new_with("# Hello\n\n    code\n", options);
+        let html = page.render_html_document();
+        assert!(html.contains("<title>Hello</title>"));
+        assert!(!html.contains("<style>") && !html.contains("math.js"));
+        assert!(html.contains("<script src=\"hl.js\"></script>"));
+        assert!(!html.contains("og:title"));
+
+        let options = PageOptions {
+            document: options::DocumentOptions {
+                math_script: Some("https://example.com/math.js".to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let page = Page::new_with("$x$", options);
+        let html = page.render_html_document();
+        assert!(html.contains(
+            "<script defer src=\"https://example.com/math.js\"></script>"
+        ));
+        assert!(!html.contains("MathML"));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let content = indoc! {r#"
+        <!---
+        title = "title"
+        date = "yesterday"
+        -->
+
+        Text with @emoji{smile;no_such_emoji} and ![missing](/no/such/image.png)
+        "#};
+        let page = Page::new(content);
+        assert!(page.meta.is_none());
+        let diagnostics = page
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| {
+                let (line, _) = diagnostic.location(&page.content);
+                (diagnostic.severity, diagnostic.code, line)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (Severity::Error, "invalid-meta", 3),
+                (Severity::Warning, "unknown-emoji", 6),
+                (Severity::Warning, "missing-image", 6),
+            ]
+        );
+
+        let page = Page::new("# Title\n\n@kbd{cmd+c} @char{x}\n");
+        assert!(page.diagnostics().is_empty());
+
+        // relative images are checked only against a known image base
+        let content = "![a](Cargo.toml) ![b](no/such/image.png)\n";
+        assert!(Page::new(content).diagnostics().is_empty());
+        let options = PageOptions {
+            image_base: Some(env!("CARGO_MANIFEST_DIR").to_owned()),
+            ..Default::default()
+        };
+        let diagnostics = Page::new_with(content, options).diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "missing-image");
+        assert!(diagnostics[0].message.contains("no/such/image.png"));
+    }
+
+    #[test]
+    fn test_safe_mode() {
+        use options::SanitizePolicy;
+
+        let options = PageOptions {
+            sanitize: SanitizePolicy::Safe,
+            ..Default::default()
+        };
+        let render =
+            |content: &str| Page::new_with(content, options.clone()).render();
+        assert_eq!(
+            render("[a](javascript:void) [b](https://example.com) [c](docs/c.html)"),
+            r#"<div><p>a <a href="https://example.com" rel="nofollow noopener">b</a> <a href="docs/c.html">c</a></p></div>"#
+        );
+        assert_eq!(
+            render("![x](javascript:void) ![y](data:image/png;base64,AA==)"),
+            "<div><p>x y</p></div>"
+        );
+        assert_eq!(
+            render(r#"![a" onerror="alert(1)](logo.png)"#),
+            r#"<div><p><img alt="a&quot; onerror=&quot;alert(1)" src="logo.png"/></p></div>"#
+        );
+        assert_eq!(
+            render("@video{<script>alert(1)</script>} $<b>$"),
+            "<div><p>&lt;script&gt;alert(1)&lt;/script&gt; $&lt;b&gt;$</p></div>"
+        );
+        // found by `test_safe_mode_fuzz`
+        assert_eq!(render("@kbd]x[{y}"), "<div><p>@kbd]x[{y}</p></div>");
+
+        let options = PageOptions {
+            sanitize: SanitizePolicy::Safe,
+            allowed_url_schemes: vec!["https".to_owned(), "data".to_owned()],
+            ..Default::default()
+        };
+        let page = Page::new_with(
+            "![y](data:image/png;base64,AA==) [a](http://example.com)",
+            options,
+        );
+        assert_eq!(
+            page.render(),
+            r#"<div><p><img alt="y" src="data:image/png;base64,AA=="/> a</p></div>"#
+        );
+    }
+
+    #[test]
+    fn test_safe_mode_fuzz() {
+        use options::SanitizePolicy;
+
+        const FRAGMENTS: &[&str] = &[
+            "<script>alert(1)</script>",
+            "<img src=x onerror=alert(1)>",
+            "\" onmouseover=\"alert(1)",
+            "' onfocus='alert(1)",
+            "javascript:alert(1)",
+            " JaVaScRiPt:alert(1)",
+            "java\tscript:alert(1)",
+            "&#106;avascript:alert(1)",
+            "vbscript:msgbox(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "https://example.com/?a=1&b=\"2\"",
+            "<iframe src=//evil>",
+            "</code></pre><style>",
+            "-->",
+            "<!--",
+            "`",
+            "*",
+            "$",
+            "@",
+            "{",
+            "}",
+            "(",
+            ")",
+            "[",
+            "]",
+            "!",
+            ">",
+            "\n",
+            "\n\n",
+            "- ",
+            "    ",
+            "# ",
+        ];
+        const TEMPLATES: &[&str] = &[
+            "{}",
+            "[{}]({})",
+            "[x]({})",
+            "![{}]({})",
+            "@kbd{{}}",
+            "@emoji{{}}",
+            "@char{{}}",
+            "@video{{}}",
+            "@note{{}}",
+            "${}$",
+            "$${}$$",
+            "`{}`",
+            "*{}*",
+            "**{}**",
+            "> {}",
+            "- {}",
+            "# {}",
+        ];
+        const ELEMENTS: &[&str] = &[
+            "div",
+            "p",
+            "h1",
+            "h2",
+            "h3",
+            "h4",
+            "h5",
+            "h6",
+            "ul",
+            "li",
+            "pre",
+            "code",
+            "em",
+            "strong",
+            "a",
+            "img",
+            "blockquote",
+            "br",
+            "kbd",
+            "span",
+        ];
+
+        fn check(node: &Handle, html: &str) {
+            if let NodeData::Element {
+                ref name,
+                ref attrs,
+                ..
+            } = node.data
+            {
+                assert!(
+                    ELEMENTS.contains(&&*name.local),
+                    "{}\n{html}",
+                    name.local
+                );
+                for attr in attrs.borrow().iter() {
+                    let key = attr.name.local.to_string();
+                    assert!(
+                        ["href", "src", "alt", "id", "class", "rel"]
+                            .contains(&key.as_str()),
+                        "{key}\n{html}"
+                    );
+                    if key == "href" || key == "src" {
+                        let url = attr
+                            .value
+                            .chars()
+                            .filter(|c| !c.is_whitespace() && !c.is_control())
+                            .collect::<String>()
+                            .to_lowercase();
+                        assert!(
+                            ["javascript:", "vbscript:", "data:"]
+                                .iter()
+                                .all(|scheme| !url.starts_with(scheme)),
+                            "{url}\n{html}"
+                        );
+                    }
+                }
+            }
+            for child in node.children.borrow().iter() {
+                check(child, html);
+            }
+        }
+
+        // xorshift, so failures are reproducible without a random crate
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut random = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        for pretty_html in [false, true] {
+            let options = PageOptions {
+                sanitize: SanitizePolicy::Safe,
+                heading_ids: true,
+                pretty_html,
+                ..Default::default()
+            };
+            for _ in 0..2000 {
+                let mut content = String::new();
+                for _ in 0..1 + random(6) {
+                    let mut piece =
+                        TEMPLATES[random(TEMPLATES.len())].to_owned();
+                    while let Some(pos) = piece.find("{}") {
+                        let fragment = FRAGMENTS[random(FRAGMENTS.len())];
+                        piece.replace_range(pos..pos + 2, fragment);
+                    }
+                    content.push_str(&piece);
+                    content.push_str(["", " ", "\n", "\n\n"][random(4)]);
+                }
+                let html = Page::new_with(&content, options.clone()).render();
+                let dom = parse_html(&html);
+                // the fragment is parsed into an `html` element
+                let root = &dom.document.children.borrow()[0];
+                for child in root.children.borrow().iter() {
+                    check(child, &html);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_list_spacing() {
+        let tight = Page::new("- a\n- *b*\n\ntext\n");
+        assert_eq!(
+            tight.render(),
+            "<div><ul><li>a</li><li><em>b</em></li></ul><p>text</p></div>"
+        );
+        // items separated by blank lines
+        let loose = Page::new("- a\n\n- b\n\ntext\n");
+        assert_eq!(
+            loose.render(),
+            "<div><ul><li><p>a</p></li><li><p>b</p></li></ul><p>text</p></div>"
+        );
+        // an item containing blocks
+        let loose = Page::new("- a\n\n        code\n- b\n");
+        assert_eq!(
+            loose.render(),
+            "<div><ul><li><p>a</p><pre><code>code</code></pre></li><li><p>b</p></li></ul></div>"
+        );
+    }
+
+    #[test]
+    fn test_valid_html() {
+        assert!(!parse_html("<pre><code>x</pre></code>").errors.is_empty());
+        assert!(!parse_html("<p><pre>x</pre></p>").errors.is_empty());
+        let contents = [
+            include_str!("../testdata/backquote_00.md"),
+            include_str!("../testdata/backquote_01.md"),
+            include_str!("../testdata/backquote_02.md"),
+            include_str!("../testdata/codeblock_00.md"),
+            include_str!("../testdata/emphasis_01.md"),
+            include_str!("../testdata/heading_00.md"),
+            include_str!("../testdata/html_char_escape_00.md"),
+            include_str!("../testdata/math_mode.md"),
+            include_str!("../testdata/para_ending_whitespace_00.md"),
+            include_str!("../testdata/para_ending_whitespace_01.md"),
+            include_str!("../testdata/zh_cn_hybrid_in_para.md"),
+            "- a\n- b\n\n- c\n\n    > quote\n\n        code\n",
+            "Press @kbd{cmd+c} or @unknown{x}\n\n![logo](logo.png) [a & b](https://example.com/?a=1&b=2)\n",
+            "# *Title*\n\n`a < b` and $$x$$\n",
+        ];
+        for content in contents {
+            for pretty_html in [false, true] {
+                let options = PageOptions {
+                    pretty_html,
+                    heading_ids: true,
+                    ..Default::default()
+                };
+                assert_valid_html(&Page::new_with(content, options).render());
+            }
+        }
+    }
+
+    #[test]
+    fn test_pretty_html() {
+        let content = indoc! {r#"
+        # Title
+
+        Some *text*
+        [here](https://example.com)
+
+        - item head
+
+            para
+
+                code
+                  more
+        - loose
+
+        > quote
+        "#};
+        let options = PageOptions {
+            pretty_html: true,
+            ..Default::default()
+        };
+        let page = Page::new_with(content, options);
+        let wanted = indoc! {r#"
+        <div>
+          <h1>Title</h1>
+          <p>Some <em>text</em> <a href="https://example.com">here</a></p>
+          <ul>
+            <li>
+              <p>item head</p>
+              <p>para</p>
+              <pre><code>code
+          more</code></pre>
+            </li>
+            <li>
+              <p>loose</p>
+            </li>
+          </ul>
+          <blockquote>
+            <p>quote</p>
+          </blockquote>
+        </div>
+        "#};
+        assert_eq!(page.render(), wanted);
+        // pretty printing only changes whitespace between block elements
+        let html = Page::new(content).render();
+        assert_eq!(
+            page.render()
+                .lines()
+                .map(str::trim_start)
+                .collect::<String>(),
+            html.lines().map(str::trim_start).collect::<String>()
+        );
+    }
+
+    #[test]
+    fn test_page_options() {
+        use options::{MathMode, SanitizePolicy, SoftBreak};
+
+        let render = |content: &str, options: PageOptions| {
+            Page::new_with(content, options).render()
+        };
+
+        let options = PageOptions {
+            heading_ids: true,
+            ..Default::default()
+        };
+        let html =
+            render("# Intro\n\n# Intro\n\n## *Usage* of `cm`\n", options);
+        assert!(html.contains(r#"<h1 id="intro">"#));
+        assert!(html.contains(r#"<h1 id="intro-1">"#));
+        assert!(html.contains(r#"<h2 id="usage-of-cm">"#));
+
+        let mut options = PageOptions::default();
+        options.extensions.math = false;
+        options.extensions.marks = false;
+        let html = render("$a$ @kbd{c}\n", options);
+        assert_eq!(html, "<div><p>$a$ @kbd{c}</p></div>");
+
+        let content = "$a<b$ and @video{<b>x</b>}\n";
+        let html = render(content, PageOptions::default());
+        assert_eq!(html, "<div><p>$a<b$ and <b>x</b></p></div>");
+        let options = PageOptions {
+            sanitize: SanitizePolicy::Escape,
+            ..Default::default()
+        };
+        let html = render(content, options);
+        assert_eq!(html, "<div><p>$a&lt;b$ and &lt;b&gt;x&lt;/b&gt;</p></div>");
+
+        let content = "first line\nsecond line\n";
+        let options = PageOptions {
+            soft_break: SoftBreak::Newline,
+            ..Default::default()
+        };
+        assert_eq!(
+            render(content, options),
+            "<div><p>first line\nsecond line</p></div>"
+        );
+        let options = PageOptions {
+            soft_break: SoftBreak::HardBreak,
+            ..Default::default()
+        };
+        assert_eq!(
+            render(content, options.clone()),
+            "<div><p>first line<br/>\nsecond line</p></div>"
+        );
+        let latex = Page::new_with(content, options).render_latex();
+        assert!(latex.contains("first line\\\\\nsecond line\n"));
+
+        let options = PageOptions {
+            image_base: Some("https://example.com/images/".to_owned()),
+            ..Default::default()
+        };
+        let html = render("![a](a.png) ![b](https://b.com/b.png)\n", options);
+        assert!(html.contains(r#"src="https://example.com/images/a.png""#));
+        assert!(html.contains(r#"src="https://b.com/b.png""#));
+
+        let content = "inline $a^2$\n\n$$b$$\n";
+        let options = PageOptions {
+            math: MathMode::Bracket,
+            ..Default::default()
+        };
+        let html = render(content, options);
+        assert!(html.contains(r"\(a^2\)") && html.contains(r"\[b\]"));
+        let options = PageOptions {
+            math: MathMode::Span,
+            ..Default::default()
+        };
+        let html = render(content, options);
+        assert!(html.contains(r#"<span class="math inline">a^2</span>"#));
+        assert!(html.contains(r#"<span class="math display">b</span>"#));
+
+        let template = std::env::temp_dir()
+            .join(format!("concisemark-template-{}.tex", std::process::id()));
+        std::fs::write(&template, "% custom preamble\n").unwrap();
+        let options = PageOptions {
+            latex_template: Some(template.clone()),
+            ..Default::default()
+        };
+        let latex = Page::new_with("hello\n", options).render_latex();
+        assert!(latex.starts_with("% custom preamble\n\\begin{document}"));
+        _ = std::fs::remove_file(template);
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let options = PageOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let cases = [
+            ("price: $5\n", "unterminated-math"),
+            ("@math{x^2\n", "unclosed-mark"),
+            ("- item\n   misaligned\n", "malformed-list"),
+            ("- item\n\n  - nested\n", "list-indent"),
+            ("<!---\ntitle = 1\n-->\n# Title\n", "invalid-meta"),
+        ];
+        for (content, code) in cases {
+            let error = Page::try_new_with(content, options.clone())
+                .err()
+                .unwrap_or_else(|| panic!("{content:?} should fail"));
+            assert_eq!(error.diagnostics[0].code, code);
+            let page = Page::new(content);
+            assert_eq!(page.diagnostics()[0].code, code);
+        }
+
+        // problems which do not affect parsing are not errors in strict mode
+        let page = Page::try_new_with("@video{a.mp4}\n", options).unwrap();
+        assert_eq!(page.diagnostics().len(), 1);
+    }
+
     #[test]
     fn test_heading_00() {
         let content = include_str!("../testdata/heading_00.md");
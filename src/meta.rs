@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::diagnostic::Diagnostic;
+
 /// You can put an optional html comment (whose body is in toml format) in the front of your markdown file
 /// ```text
 /// <!---
@@ -30,34 +32,91 @@ impl Meta {
     const META_END_MARK: &'static str = "-->\n";
 
     pub fn new<S: AsRef<str>>(content: S) -> Option<Self> {
+        Self::parse(content).ok().flatten()
+    }
+
+    /// Like [`Meta::new`] but returns a diagnostic if the meta comment exists but is not valid
+    pub fn parse<S: AsRef<str>>(
+        content: S,
+    ) -> Result<Option<Self>, Diagnostic> {
         let content = content.as_ref();
         let text = content.trim_start();
         if !text.starts_with(Self::META_START_MARK) {
-            return None;
+            return Ok(None);
         }
 
         let start_index = content.len() - text.len();
         let end_index = if let Some(pos) = text.find(Self::META_END_MARK) {
             start_index + pos + Self::META_END_MARK.len()
         } else {
-            return None;
+            return Ok(None);
         };
 
         let meta_start = start_index + Self::META_START_MARK.len();
         let meta_end = end_index - Self::META_END_MARK.len();
         let meta_text = &content[meta_start..meta_end];
-        if let Ok(mut meta) = toml::from_str::<Meta>(meta_text) {
-            meta.size = end_index;
-            return Some(meta);
-        } else {
-            log::error!("failed to parse meta text: {meta_text}");
+        match toml::from_str::<Meta>(meta_text) {
+            Ok(mut meta) => {
+                meta.size = end_index;
+                Ok(Some(meta))
+            }
+            Err(e) => {
+                let message = e.to_string();
+                let message = match message.find(" at line ") {
+                    Some(pos) => &message[..pos],
+                    None => message.as_str(),
+                };
+                // toml reports the start of the table for invalid values, so we find the line of
+                // the key by ourselves
+                let key = message
+                    .split_once("for key `")
+                    .and_then(|(_, key)| key.split_once('`'))
+                    .map(|(key, _)| key);
+                let mut offset = meta_start;
+                let mut range = start_index..end_index;
+                for (i, line) in meta_text.split_inclusive('\n').enumerate() {
+                    let matched = match key {
+                        Some(key) => {
+                            line.trim_start().strip_prefix(key).is_some_and(
+                                |rest| rest.trim_start().starts_with('='),
+                            )
+                        }
+                        None => e.line_col().is_some_and(|(n, _)| n == i),
+                    };
+                    if matched {
+                        range = offset..(offset + line.trim_end().len());
+                        break;
+                    }
+                    offset += line.len();
+                }
+                Err(Diagnostic::error(
+                    "invalid-meta",
+                    format!("failed to parse meta: {message}, it is treated as text"),
+                    range,
+                ))
+            }
+        }
+    }
+
+    /// Serialize meta back into the html comment which can be put in the front of markdown file
+    pub fn to_comment(&self) -> String {
+        match toml::to_string(self) {
+            Ok(text) => format!(
+                "{}{}{}",
+                Self::META_START_MARK,
+                text,
+                Self::META_END_MARK
+            ),
+            Err(e) => {
+                log::error!("failed to serialize meta: {e:?}");
+                "".to_owned()
+            }
         }
-        None
     }
 }
 
 mod serde_meta_date {
-    use chrono::{DateTime, TimeZone, Utc};
+    use chrono::{DateTime, NaiveDateTime, Utc};
     use serde::{self, Deserialize, Deserializer, Serializer};
 
     const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
@@ -80,7 +139,8 @@ mod serde_meta_date {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Utc.datetime_from_str(&s, FORMAT)
+        NaiveDateTime::parse_from_str(&s, FORMAT)
+            .map(|date| date.and_utc())
             .map_err(serde::de::Error::custom)
     }
 }
@@ -21,6 +21,28 @@ pub fn find_nodes_by_tag(node: &Node, tag: NodeTagName) -> Vec<Node> {
     r
 }
 
+/// Plain text of inline children of `node` such as a heading, markups are removed and whitespace
+/// is collapsed
+pub fn inline_text(node: &Node, content: &str) -> String {
+    node.children()
+        .iter()
+        .map(|child| {
+            let nodedata = child.data.borrow();
+            match nodedata.tag.name {
+                NodeTagName::Link | NodeTagName::Image => {
+                    child.get_attr_or("name", "")
+                }
+                _ => content[nodedata.range.clone()]
+                    .trim_matches(|c| matches!(c, '*' | '`' | '$'))
+                    .to_owned(),
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
 /// An AST node
 ///
 /// It seems impossible to use only one structure to implement feature that
@@ -71,6 +93,26 @@ impl Node {
         }
     }
 
+    /// Convert node into json value, node text is included if `content` is given
+    pub fn to_json(&self, content: Option<&str>) -> serde_json::Value {
+        let nodedata = self.data.borrow();
+        let range = &nodedata.range;
+        let mut value = serde_json::json!({
+            "name": format!("{:?}", nodedata.tag.name),
+            "attrs": nodedata.tag.attrs,
+            "range": [range.start, range.end],
+        });
+        if let Some(content) = content {
+            value["text"] = content[range.start..range.end].into();
+        }
+        value["children"] = self
+            .children()
+            .iter()
+            .map(|child| child.to_json(content))
+            .collect();
+        value
+    }
+
     pub fn rc(&self) -> Rc<RefCell<NodeData>> {
         Rc::clone(&self.data)
     }
@@ -110,7 +152,7 @@ impl Node {
         children
     }
 
-    pub fn read(&self) -> Ref<NodeData> {
+    pub fn read(&self) -> Ref<'_, NodeData> {
         self.data.borrow()
     }
 
@@ -118,6 +160,10 @@ impl Node {
         self.data.borrow().range.end - self.data.borrow().range.start
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn transform<F, E>(&self, hook: &F)
     where
         F: Fn(&Node) -> Result<(), E>,
@@ -185,7 +231,7 @@ pub struct NodeData {
 }
 
 /// Meta information for [`Node`]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NodeTag {
     /// Node name
     pub name: NodeTagName,
@@ -0,0 +1,209 @@
+//! Page options
+//!
+//! [`PageOptions`] can be deserialized, so it can live in a project config file, for example
+//!
+//!     use concisemark::options::{MathMode, PageOptions};
+//!
+//!     let options: PageOptions = toml::from_str(r#"
+//!         heading_ids = true
+//!         math = "span"
+//!
+//!         [extensions]
+//!         marks = false
+//!     "#).unwrap();
+//!     assert_eq!(options.math, MathMode::Span);
+//!     assert!(options.extensions.math && !options.extensions.marks);
+//!
+//! Missing fields take their default values.
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+/// Options of a [`Page`](crate::Page)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageOptions {
+    /// Fail on any problem found when parsing instead of rendering the malformed input as text,
+    /// see [`Page::try_new_with`](crate::Page::try_new_with)
+    pub strict: bool,
+    /// Syntax extensions to parse
+    pub extensions: Extensions,
+    /// Generate an `id` attribute for each heading from its text, duplicated ids are suffixed
+    /// with `-1`, `-2` ...
+    pub heading_ids: bool,
+    /// How raw text from marks and math is written into HTML
+    pub sanitize: SanitizePolicy,
+    /// Url schemes allowed for links and images in [`SanitizePolicy::Safe`] mode, relative urls
+    /// are always allowed
+    pub allowed_url_schemes: Vec<String>,
+    /// How line breaks inside a paragraph are rendered
+    pub soft_break: SoftBreak,
+    /// Base path or url prepended to relative image paths
+    pub image_base: Option<String>,
+    /// LaTeX preamble file used instead of the bundled one
+    pub latex_template: Option<PathBuf>,
+    /// How math is written into HTML
+    pub math: MathMode,
+    /// Keep urls of links after their text as `text (url)` in plain text output, see
+    /// [`Page::render_text`](crate::Page::render_text)
+    pub text_urls: bool,
+    /// Put HTML block elements on their own lines, indented by their depth
+    pub pretty_html: bool,
+    /// Head and assets of standalone HTML documents, see
+    /// [`Page::render_html_document`](crate::Page::render_html_document)
+    pub document: DocumentOptions,
+}
+
+impl Default for PageOptions {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            extensions: Extensions::default(),
+            heading_ids: false,
+            sanitize: SanitizePolicy::None,
+            allowed_url_schemes: vec![
+                "http".to_owned(),
+                "https".to_owned(),
+                "mailto".to_owned(),
+            ],
+            soft_break: SoftBreak::Space,
+            image_base: None,
+            latex_template: None,
+            math: MathMode::Dollar,
+            text_urls: false,
+            pretty_html: false,
+            document: DocumentOptions::default(),
+        }
+    }
+}
+
+impl PageOptions {
+    /// Resolve image `src` against [`PageOptions::image_base`], urls and absolute paths are
+    /// kept as is
+    pub fn image_src(&self, src: &str) -> String {
+        match &self.image_base {
+            Some(base) if !base.is_empty() && is_relative(src) => {
+                format!("{}/{}", base.trim_end_matches('/'), src)
+            }
+            _ => src.to_owned(),
+        }
+    }
+
+    /// Whether `url` is allowed for links and images, urls of any scheme are allowed unless
+    /// [`PageOptions::sanitize`] is [`SanitizePolicy::Safe`]
+    ///
+    ///     use concisemark::options::{PageOptions, SanitizePolicy};
+    ///
+    ///     let options = PageOptions { sanitize: SanitizePolicy::Safe, ..Default::default() };
+    ///     assert!(options.allows_url("https://example.com"));
+    ///     assert!(options.allows_url("docs/intro.html"));
+    ///     assert!(!options.allows_url("javascript:alert(1)"));
+    pub fn allows_url(&self, url: &str) -> bool {
+        if self.sanitize != SanitizePolicy::Safe {
+            return true;
+        }
+        match utils::url_scheme(url) {
+            Some(scheme) => self
+                .allowed_url_schemes
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&scheme)),
+            None => true,
+        }
+    }
+}
+
+/// Whether `src` is a relative path rather than an absolute path or url
+fn is_relative(src: &str) -> bool {
+    !src.is_empty()
+        && !src.starts_with('/')
+        && !src.starts_with("data:")
+        && !src.contains("://")
+}
+
+/// Syntax extensions of ConciseMark, disabled syntax is parsed as text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Extensions {
+    /// Math in `$` such as `$a^2$`
+    pub math: bool,
+    /// Marks such as `@kbd{cmd+c}`
+    pub marks: bool,
+    /// Emphasis in `*` such as `*italics*` and `**bold**`
+    pub emphasis: bool,
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Self {
+            math: true,
+            marks: true,
+            emphasis: true,
+        }
+    }
+}
+
+/// Options of standalone HTML documents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentOptions {
+    /// Inline a stylesheet into the document
+    pub theme: bool,
+    /// CSS file inlined instead of the bundled stylesheet
+    pub stylesheet: Option<PathBuf>,
+    /// Url of the script rendering math such as MathJax, the bundled script rendering math into
+    /// MathML is inlined if it is not set. Nothing is included for pages without math.
+    pub math_script: Option<String>,
+    /// Url of the script highlighting code blocks, `hljs.highlightAll()` is called once the page
+    /// is loaded if it is [highlight.js](https://highlightjs.org)
+    pub highlight_script: Option<String>,
+}
+
+impl Default for DocumentOptions {
+    fn default() -> Self {
+        Self {
+            theme: true,
+            stylesheet: None,
+            math_script: None,
+            highlight_script: None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SanitizePolicy {
+    /// Values of marks which are not rendered specially and math are written as is, so they can
+    /// embed raw HTML
+    None,
+    /// Values of marks and math are escaped
+    Escape,
+    /// Like [`SanitizePolicy::Escape`] for untrusted input, urls of links and images whose
+    /// schemes are not in [`PageOptions::allowed_url_schemes`] are dropped, and links to other
+    /// sites get `rel="nofollow noopener"`
+    Safe,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SoftBreak {
+    /// Join lines with a space, or nothing between two east asian characters
+    Space,
+    /// Keep line breaks
+    Newline,
+    /// Render line breaks as `<br/>` in HTML and `\\` in LaTeX
+    HardBreak,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MathMode {
+    /// `$...$` for inline math and `$$...$$` for display math
+    Dollar,
+    /// `\(...\)` for inline math and `\[...\]` for display math
+    Bracket,
+    /// `<span class="math inline">...</span>` and `<span class="math display">...</span>`
+    /// with escaped math
+    Span,
+}
@@ -0,0 +1,437 @@
+use super::{
+    mark,
+    markdown::{is_closing_punctuation, is_spaced},
+    push_blank_line, RenderType,
+};
+use crate::{
+    node::{Emphasis, Node, NodeTagName},
+    options::{PageOptions, SanitizePolicy, SoftBreak},
+    utils,
+};
+
+/// SGR codes which turn a style on and off, styles are turned off by their own codes so outer
+/// styles (such as a colored heading) are kept
+type Style = (&'static str, &'static str);
+
+const HEADING1: Style = ("\x1b[1;4;35m", "\x1b[22;24;39m");
+const HEADING2: Style = ("\x1b[1;36m", "\x1b[22;39m");
+const HEADING: Style = ("\x1b[1m", "\x1b[22m");
+const BOLD: Style = ("\x1b[1m", "\x1b[22m");
+const ITALICS: Style = ("\x1b[3m", "\x1b[23m");
+const CODE: Style = ("\x1b[33m", "\x1b[39m");
+const MATH: Style = ("\x1b[32m", "\x1b[39m");
+const LINK: Style = ("\x1b[4;34m", "\x1b[24;39m");
+const DIM: Style = ("\x1b[2m", "\x1b[22m");
+
+/// Bullets of list items by nesting level
+const BULLETS: &[&str] = &["•", "◦", "▪"];
+
+/// Render the AST into text for terminals
+///
+/// Paragraphs are wrapped to fit in `width` columns (east asian characters take two columns),
+/// emphasis, code, math and headings are styled by SGR codes, links are OSC 8 hyperlinks, code
+/// blocks are boxed and list items are indented with bullets.
+pub fn generate<S: AsRef<str>>(
+    node: &Node,
+    content: S,
+    options: &PageOptions,
+    width: usize,
+) -> String {
+    let mut text = String::new();
+    let renderer = Renderer {
+        content: content.as_ref(),
+        options,
+    };
+    renderer.generate_block(node, 0, 0, width, &mut text);
+    let text = text.trim_end();
+    if text.is_empty() {
+        return "".to_owned();
+    }
+    format!("{text}\n")
+}
+
+/// An unbreakable piece of text
+struct Word {
+    /// Text with SGR codes and hyperlinks
+    styled: String,
+    width: usize,
+    /// Whether it is separated from the previous word by a space
+    spaced: bool,
+    /// Whether a line must be broken before it
+    broken: bool,
+    /// The last visible character
+    previous: char,
+}
+
+/// Whitespace seen since the last word
+#[derive(Clone, Copy, PartialEq)]
+enum Gap {
+    None,
+    Space,
+    Newline,
+}
+
+struct Renderer<'a> {
+    content: &'a str,
+    options: &'a PageOptions,
+}
+
+impl<'a> Renderer<'a> {
+    fn generate_block(
+        &self,
+        node: &Node,
+        indent: usize,
+        depth: usize,
+        width: usize,
+        text: &mut String,
+    ) {
+        let nodedata = node.data.borrow();
+        let indentstr = " ".repeat(indent);
+        match nodedata.tag.name {
+            NodeTagName::Section | NodeTagName::ListBody => {
+                for child in node.children().iter() {
+                    if child.data.borrow().tag.name == NodeTagName::BlankLine {
+                        continue;
+                    }
+                    if !text.is_empty() {
+                        push_blank_line(text);
+                    }
+                    self.generate_block(child, indent, depth, width, text);
+                }
+            }
+            NodeTagName::List => {
+                let bullet = BULLETS[depth.min(BULLETS.len() - 1)];
+                for item in node.children().iter() {
+                    for child in item.children().iter() {
+                        if child.data.borrow().tag.name == NodeTagName::ListHead
+                        {
+                            let words = self.words(child);
+                            let lines =
+                                wrap(words, width.saturating_sub(indent + 2));
+                            for (i, line) in lines.iter().enumerate() {
+                                let prefix = if i == 0 { bullet } else { " " };
+                                text.push_str(&format!(
+                                    "{indentstr}{prefix} {line}\n"
+                                ));
+                            }
+                        } else {
+                            let mut body = String::new();
+                            self.generate_block(
+                                child,
+                                indent + 2,
+                                depth + 1,
+                                width,
+                                &mut body,
+                            );
+                            if !body.trim().is_empty() {
+                                push_blank_line(text);
+                                text.push_str(&body);
+                                push_blank_line(text);
+                            }
+                        }
+                    }
+                }
+            }
+            NodeTagName::Code => {
+                let body = &self.content[nodedata.range.clone()];
+                let code = utils::remove_indent(body).replace('\t', "    ");
+                let lines = code
+                    .trim_end()
+                    .lines()
+                    .map(strip_controls)
+                    .collect::<Vec<String>>();
+                let inner = lines
+                    .iter()
+                    .map(|line| utils::display_width(line))
+                    .max()
+                    .unwrap_or(0);
+                let border = "─".repeat(inner + 2);
+                text.push_str(&format!("{indentstr}┌{border}┐\n"));
+                for line in lines {
+                    let padding = inner - utils::display_width(&line);
+                    text.push_str(&format!(
+                        "{indentstr}│ {line}{} │\n",
+                        " ".repeat(padding)
+                    ));
+                }
+                text.push_str(&format!("{indentstr}└{border}┘\n"));
+            }
+            NodeTagName::Heading => {
+                let level = node
+                    .get_attr_or("level", "1")
+                    .parse::<usize>()
+                    .unwrap_or(1);
+                let style = match level {
+                    1 => HEADING1,
+                    2 => HEADING2,
+                    _ => HEADING,
+                };
+                for line in wrap(self.words(node), width.saturating_sub(indent))
+                {
+                    text.push_str(&format!(
+                        "{indentstr}{}{line}{}\n",
+                        style.0, style.1
+                    ));
+                }
+            }
+            NodeTagName::BlankLine => {}
+            _ => {
+                let words = self.words(node);
+                let is_quote = nodedata.tag.name == NodeTagName::Para
+                    && self.content[nodedata.range.clone()]
+                        .trim_start()
+                        .starts_with('>');
+                if is_quote {
+                    for line in wrap(words, width.saturating_sub(indent + 2)) {
+                        text.push_str(&format!(
+                            "{indentstr}{}│{} {line}\n",
+                            DIM.0, DIM.1
+                        ));
+                    }
+                } else {
+                    for line in wrap(words, width.saturating_sub(indent)) {
+                        text.push_str(&format!("{indentstr}{line}\n"));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Split inline children of `node` into words
+    fn words(&self, node: &Node) -> Vec<Word> {
+        let is_quote = node.data.borrow().tag.name == NodeTagName::Para
+            && self.content[node.data.borrow().range.clone()]
+                .trim_start()
+                .starts_with('>');
+        let mut words = vec![];
+        let mut gap = Gap::None;
+        let mut line_start = true;
+        for child in node.children().iter() {
+            let nodedata = child.data.borrow();
+            let body = &self.content[nodedata.range.clone()];
+            match nodedata.tag.name {
+                NodeTagName::Text => {
+                    for (i, line) in body.split('\n').enumerate() {
+                        if i > 0 {
+                            gap = Gap::Newline;
+                            line_start = true;
+                        }
+                        let mut line = line;
+                        if is_quote && line_start {
+                            let trimmed = line.trim_start();
+                            line = trimmed.strip_prefix('>').unwrap_or(trimmed);
+                        }
+                        if !line.is_empty() {
+                            line_start = false;
+                        }
+                        gap = self.push_text(&mut words, line, None, gap);
+                    }
+                }
+                NodeTagName::Emphasis(t) => {
+                    let style = match t {
+                        Emphasis::Italics => ITALICS,
+                        Emphasis::Bold => BOLD,
+                    };
+                    let body = body.trim_matches('*');
+                    gap = self.push_text(&mut words, body, Some(style), gap);
+                }
+                NodeTagName::Code => {
+                    let body = body.trim_matches('`').trim();
+                    self.push_atom(&mut words, body, CODE, gap);
+                    gap = Gap::None;
+                }
+                NodeTagName::Math => {
+                    let body = body.trim_matches('$').trim();
+                    self.push_atom(&mut words, body, MATH, gap);
+                    gap = Gap::None;
+                }
+                NodeTagName::Link => {
+                    let url = child.get_attr_or("href", "");
+                    let mut name =
+                        strip_controls(&child.get_attr_or("name", ""));
+                    if name.is_empty() {
+                        name = strip_controls(&url);
+                    }
+                    let start = words.len();
+                    gap = self.push_text(&mut words, &name, Some(LINK), gap);
+                    for word in words[start..].iter_mut() {
+                        word.styled = hyperlink(&url, &word.styled);
+                    }
+                }
+                NodeTagName::Image => {
+                    let src = child.get_attr_or("src", "");
+                    let alt = format!(
+                        "[image: {}]",
+                        strip_controls(&child.get_attr_or("name", ""))
+                    );
+                    let start = words.len();
+                    gap = self.push_text(&mut words, &alt, Some(DIM), gap);
+                    for word in words[start..].iter_mut() {
+                        word.styled = hyperlink(&src, &word.styled);
+                    }
+                }
+                NodeTagName::Extension => {
+                    let value = mark::generate(
+                        body,
+                        RenderType::Text,
+                        SanitizePolicy::None,
+                    )
+                    .unwrap_or_else(|| body.to_owned());
+                    gap = self.push_text(&mut words, value.trim(), None, gap);
+                }
+                _ => {
+                    gap = self.push_text(&mut words, body, None, gap);
+                }
+            }
+        }
+        words
+    }
+
+    /// Split `text` into words, returns the gap after the last word
+    fn push_text(
+        &self,
+        words: &mut Vec<Word>,
+        text: &str,
+        style: Option<Style>,
+        mut gap: Gap,
+    ) -> Gap {
+        let push = |words: &mut Vec<Word>, word: &str, gap: Gap| {
+            let styled = match style {
+                Some((on, off)) => format!("{on}{word}{off}"),
+                None => word.to_owned(),
+            };
+            self.push_word(words, styled, word, gap);
+        };
+        let mut word = String::new();
+        let mut word_gap = gap;
+        for ch in text.chars() {
+            // control characters such as ESC would inject escape sequences
+            if ch.is_control() && !ch.is_whitespace() {
+                continue;
+            }
+            if ch.is_whitespace() {
+                if !word.is_empty() {
+                    push(words, &word, word_gap);
+                    word.clear();
+                    gap = Gap::None;
+                }
+                if gap != Gap::Newline {
+                    gap = if ch == '\n' { Gap::Newline } else { Gap::Space };
+                }
+                continue;
+            }
+            // east asian characters can be broken without whitespace
+            if let Some(previous) = word.chars().last() {
+                if (!is_spaced(previous) || !is_spaced(ch))
+                    && !is_closing_punctuation(ch)
+                {
+                    push(words, &word, word_gap);
+                    word.clear();
+                    gap = Gap::None;
+                }
+            }
+            if word.is_empty() {
+                word_gap = gap;
+            }
+            word.push(ch);
+        }
+        if !word.is_empty() {
+            push(words, &word, word_gap);
+            gap = Gap::None;
+        }
+        gap
+    }
+
+    /// Push a word which is never broken
+    fn push_atom(
+        &self,
+        words: &mut Vec<Word>,
+        text: &str,
+        style: Style,
+        gap: Gap,
+    ) {
+        let text = strip_controls(text);
+        if !text.is_empty() {
+            let styled = format!("{}{text}{}", style.0, style.1);
+            self.push_word(words, styled, &text, gap);
+        }
+    }
+
+    /// Push a word whose visible text is `text`, it is glued to the last word if there is no
+    /// whitespace between them unless they are east asian characters
+    fn push_word(
+        &self,
+        words: &mut Vec<Word>,
+        styled: String,
+        text: &str,
+        gap: Gap,
+    ) {
+        let width = utils::display_width(text);
+        let first = text.chars().next().unwrap_or(' ');
+        let last_char = text.chars().last().unwrap_or(' ');
+        let (mut spaced, mut broken) = (false, false);
+        if let Some(last) = words.last_mut() {
+            let joined = is_spaced(last.previous) || is_spaced(first);
+            match gap {
+                Gap::None if joined || is_closing_punctuation(first) => {
+                    last.styled.push_str(&styled);
+                    last.width += width;
+                    last.previous = last_char;
+                    return;
+                }
+                Gap::None => {}
+                Gap::Space => spaced = true,
+                // a line break between two east asian characters is not a space
+                Gap::Newline => match self.options.soft_break {
+                    SoftBreak::Space => spaced = joined,
+                    SoftBreak::Newline | SoftBreak::HardBreak => broken = true,
+                },
+            }
+        }
+        words.push(Word {
+            styled,
+            width,
+            spaced,
+            broken,
+            previous: last_char,
+        });
+    }
+}
+
+/// Break `words` into lines whose display width are not greater than `width` if possible
+fn wrap(words: Vec<Word>, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut line_width = 0;
+    for word in words {
+        let sep = if word.spaced { 1 } else { 0 };
+        if line_width > 0
+            && (word.broken || line_width + sep + word.width > width)
+        {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+        }
+        if line_width > 0 && word.spaced {
+            line.push(' ');
+            line_width += 1;
+        }
+        line.push_str(&word.styled);
+        line_width += word.width;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Wrap `text` into an OSC 8 hyperlink to `url`, control characters in `url` are removed so that
+/// it can not end the sequence early
+fn hyperlink(url: &str, text: &str) -> String {
+    let url = strip_controls(url);
+    format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
+/// Remove control characters such as ESC from `text`
+fn strip_controls(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
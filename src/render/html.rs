@@ -1,12 +1,14 @@
-use super::{mark, RenderType};
+use super::{mark, markdown::is_spaced, RenderType};
 use crate::{
     node::{Emphasis, Node, NodeTagName},
+    options::{MathMode, PageOptions, SanitizePolicy, SoftBreak},
     utils,
 };
 
 pub fn generate<S: AsRef<str>, F>(
     node: &Node,
     content: S,
+    options: &PageOptions,
     hook: Option<&F>,
 ) -> String
 where
@@ -31,68 +33,7 @@ where
     // characters body as its value.
     //
     match tagname {
-        NodeTagName::Text => {
-            let mut text = String::new();
-            let line_count = body.lines().count();
-            let mut previous_line_trimmed = false;
-            for (i, line) in body.lines().enumerate() {
-                let line = line.trim_start();
-                if line.is_empty() {
-                    continue;
-                }
-                let mut is_backquote_only_line = false;
-                let ch = line.chars().next().unwrap();
-                if ch == '>' {
-                    // if the first character of paragraph line is backquote character
-                    // and it contains more text, this is case like following
-                    //
-                    //     > some text
-                    //
-                    if line.trim() != ">" {
-                        text.push_str(&utils::escape_to_html(line[1..].trim()));
-                    } else {
-                        // or else the line contains only a single `>` character such as
-                        //
-                        //     >
-                        //
-                        // but if the original line is
-                        //
-                        //     > *line*
-                        //
-                        // we will only see `> `, should we put a <br/> here?
-                        // fortunately, this case will always be the last line!
-                        if i + 1 != line_count {
-                            is_backquote_only_line = true;
-                            text.push_str("<br/>");
-                        }
-                    }
-                } else {
-                    // see test `test_para_ending_whitesapce 1)`
-                    if previous_line_trimmed
-                        && (ch.is_ascii_alphanumeric()
-                            || ch.is_ascii_punctuation()
-                            || ch.is_ascii_whitespace())
-                    {
-                        text.push(' ');
-                    }
-                    text.push_str(&utils::escape_to_html(line.trim()));
-                }
-                // see test `test_para_ending_whitesapce 2)` and `test_backquote_unicode`
-                if let Some(ch) = line.trim_end().chars().last() {
-                    if (ch.is_ascii_alphanumeric()
-                        || ch.is_ascii_punctuation()
-                        || ch.is_ascii_whitespace())
-                        && (!is_backquote_only_line)
-                    {
-                        text.push(' ');
-                        previous_line_trimmed = false;
-                    } else {
-                        previous_line_trimmed = true;
-                    }
-                }
-            }
-            return text;
-        }
+        NodeTagName::Text => return generate_text(node, content, options),
         NodeTagName::Code => {
             if node.is_inlined(content) {
                 return format!(
@@ -102,19 +43,45 @@ where
                     )
                 );
             } else {
-                return format!(
-                    "<pre><code>{}</pre></code>",
+                let html = format!(
+                    "<pre><code>{}</code></pre>",
                     utils::escape_to_html(utils::remove_indent(body).trim())
                 );
+                if options.pretty_html {
+                    // lines of code can not be indented
+                    return format!("{}{html}\n", indent(depth(node)));
+                }
+                return html;
             }
         }
         NodeTagName::Math => {
             let body = body.trim_matches(|x| x == '$');
-            if node.is_inlined(content) {
-                return format!("${body}$");
+            let body = if options.math == MathMode::Span
+                || options.sanitize != SanitizePolicy::None
+            {
+                utils::escape_to_html(body)
             } else {
-                return format!("$${body}$$");
-            }
+                body.to_owned()
+            };
+            let inlined = node.is_inlined(content);
+            return match (options.math, inlined) {
+                (MathMode::Dollar, true) => format!("${body}$"),
+                (MathMode::Dollar, false) => format!("$${body}$$"),
+                (MathMode::Bracket, true) => format!("\\({body}\\)"),
+                (MathMode::Bracket, false) => format!("\\[{body}\\]"),
+                (MathMode::Span, true) => {
+                    format!(
+                        r#"<span class="math inline">{}</span>"#,
+                        body.trim()
+                    )
+                }
+                (MathMode::Span, false) => {
+                    format!(
+                        r#"<span class="math display">{}</span>"#,
+                        body.trim()
+                    )
+                }
+            };
         }
         NodeTagName::Link => {
             let url = node.get_attr_or("href", "");
@@ -122,15 +89,32 @@ where
             if name.is_empty() {
                 name = url.clone();
             }
+            if !options.allows_url(&url) {
+                log::warn!("link to {url} is not allowed");
+                return utils::escape_to_html(&name);
+            }
+            // links to other sites of untrusted input should not be endorsed
+            let rel = if options.sanitize == SanitizePolicy::Safe
+                && (utils::url_scheme(&url).is_some()
+                    || url.trim_start().starts_with("//"))
+            {
+                r#" rel="nofollow noopener""#
+            } else {
+                ""
+            };
             return format!(
-                r#" <a href="{}">{}</a> "#,
+                r#"<a href="{}"{rel}>{}</a>"#,
                 utils::escape_html_double_quote(&url),
                 utils::escape_to_html(&name)
             );
         }
         NodeTagName::Image => {
             let alt = node.get_attr_or("name", "image link is broken");
-            let src = node.get_attr_or("src", "");
+            let src = options.image_src(&node.get_attr_or("src", ""));
+            if !options.allows_url(&src) {
+                log::warn!("image {src} is not allowed");
+                return utils::escape_to_html(&alt);
+            }
             return format!(
                 r#"<img alt="{}" src="{}"/>"#,
                 utils::escape_html_double_quote(&alt),
@@ -143,17 +127,17 @@ where
                 Emphasis::Bold => "strong",
             };
             let body = utils::escape_to_html(body.trim_matches('*'));
-            return format!(r#"<{tag}> {body} </{tag}>"#);
+            return format!(r#"<{tag}>{body}</{tag}>"#);
         }
         NodeTagName::Extension => {
-            if let Some(value) = mark::generate(body, RenderType::Html) {
+            if let Some(value) =
+                mark::generate(body, RenderType::Html, options.sanitize)
+            {
                 return value;
             } else {
                 log::warn!("unsupported mark element: {}", body);
-                return format!(
-                    "<pre><code>{}</pre></code>",
-                    utils::escape_to_html(body)
-                );
+                // marks are inline elements, so they can not be put into `pre`
+                return format!("<code>{}</code>", utils::escape_to_html(body));
             }
         }
         _ => {}
@@ -186,11 +170,22 @@ where
         NodeTagName::Image => Some("img".to_owned()),
         NodeTagName::List => Some("ul".to_owned()),
         NodeTagName::ListItem => Some("li".to_owned()),
+        NodeTagName::ListHead if is_loose_head(node) => Some("p".to_owned()),
         _ => None,
     };
+    let is_quote =
+        tagname == NodeTagName::Para && body.trim_start().starts_with('>');
     let (start_tag, end_tag) = if let Some(mark) = markup {
-        if tagname == NodeTagName::Para && body.trim_start().starts_with('>') {
+        if is_quote {
             ("<blockquote><p>".to_owned(), "</p></blockquote>".to_owned())
+        } else if let Some(id) = nodedata.tag.attrs.get("id") {
+            (
+                format!(
+                    r#"<{mark} id="{}">"#,
+                    utils::escape_html_double_quote(id)
+                ),
+                format!("</{mark}>"),
+            )
         } else {
             (format!("<{mark}>"), format!("</{mark}>"))
         }
@@ -198,16 +193,241 @@ where
         ("".to_owned(), "".to_owned())
     };
 
+    let children = node
+        .children()
+        .into_iter()
+        .filter(|x| {
+            let (start, end) =
+                (x.data.borrow().range.start, x.data.borrow().range.end);
+            // whitespace between inline elements is kept
+            x.data.borrow().tag.name == NodeTagName::Text
+                || !content[start..end].trim().is_empty()
+        })
+        .collect::<Vec<_>>();
+    if options.pretty_html && !start_tag.is_empty() {
+        let depth = depth(node);
+        let inline = |child: &Node| !is_block(child, content);
+        if is_quote {
+            let inlines = children
+                .iter()
+                .map(|child| generate(child, content, options, hook))
+                .collect::<String>();
+            return format!(
+                "{0}<blockquote>\n{1}<p>{inlines}</p>\n{0}</blockquote>\n",
+                indent(depth),
+                indent(depth + 1)
+            );
+        }
+        if children.iter().all(inline) {
+            let inlines = children
+                .iter()
+                .map(|child| generate(child, content, options, hook))
+                .collect::<String>();
+            return format!("{}{start_tag}{inlines}{end_tag}\n", indent(depth));
+        }
+        // block elements are put on their own lines, and so are runs of inline elements
+        // between them
+        let mut html = format!("{}{start_tag}\n", indent(depth));
+        let mut inlines = String::new();
+        let flush = |html: &mut String, inlines: &mut String| {
+            if !inlines.trim().is_empty() {
+                html.push_str(&indent(depth + 1));
+                html.push_str(inlines.trim());
+                html.push('\n');
+            }
+            inlines.clear();
+        };
+        for child in children.iter() {
+            let output = generate(child, content, options, hook);
+            if inline(child) {
+                inlines.push_str(&output);
+                continue;
+            }
+            flush(&mut html, &mut inlines);
+            if output.ends_with('\n') {
+                html.push_str(&output);
+            } else if !output.is_empty() {
+                // output of the hook
+                html.push_str(&format!("{}{output}\n", indent(depth + 1)));
+            }
+        }
+        flush(&mut html, &mut inlines);
+        html.push_str(&format!("{}{end_tag}\n", indent(depth)));
+        return html;
+    }
+
     let mut html = String::new();
     html += &start_tag;
-    for child in node.children().iter().filter(|x| {
-        let (start, end) =
-            (x.data.borrow().range.start, x.data.borrow().range.end);
-        !content[start..end].trim().is_empty()
-    }) {
-        html.push_str(generate(child, content, hook).as_str());
+    for child in children.iter() {
+        html.push_str(generate(child, content, options, hook).as_str());
     }
     html += &end_tag;
 
     html
 }
+
+/// Whether `node` is rendered as block elements
+fn is_block(node: &Node, content: &str) -> bool {
+    match node.data.borrow().tag.name {
+        NodeTagName::Section
+        | NodeTagName::Para
+        | NodeTagName::Heading
+        | NodeTagName::List
+        | NodeTagName::ListItem
+        | NodeTagName::ListBody => true,
+        NodeTagName::ListHead => is_loose_head(node),
+        NodeTagName::Code => !node.is_inlined(content),
+        _ => false,
+    }
+}
+
+/// Whether `head` is the head of an item in a loose list, heads of loose lists are wrapped in
+/// paragraphs
+fn is_loose_head(head: &Node) -> bool {
+    parent(head)
+        .and_then(|item| parent(&item))
+        .is_some_and(|list| is_loose(&list))
+}
+
+/// Whether items of `list` are separated by blank lines, or any of them contains blocks
+fn is_loose(list: &Node) -> bool {
+    let items = list.children();
+    items.iter().enumerate().any(|(i, item)| {
+        item.children()
+            .iter()
+            .filter(|child| {
+                child.data.borrow().tag.name == NodeTagName::ListBody
+            })
+            .flat_map(|body| body.children())
+            .any(|block| {
+                block.data.borrow().tag.name != NodeTagName::BlankLine
+                    || i + 1 < items.len()
+            })
+    })
+}
+
+fn parent(node: &Node) -> Option<Node> {
+    node.data
+        .borrow()
+        .parent
+        .upgrade()
+        .map(|data| Node { data })
+}
+
+/// Number of ancestors of `node` which are rendered as HTML elements
+fn depth(node: &Node) -> usize {
+    let mut depth = 0;
+    let mut parent = node.data.borrow().parent.upgrade();
+    while let Some(data) = parent {
+        if matches!(
+            data.borrow().tag.name,
+            NodeTagName::Section
+                | NodeTagName::Para
+                | NodeTagName::Heading
+                | NodeTagName::List
+                | NodeTagName::ListItem
+        ) {
+            depth += 1;
+        }
+        parent = data.borrow().parent.upgrade();
+    }
+    depth
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+/// Render a text node, whitespace in a line is kept as is, and line breaks are rendered by
+/// [`SoftBreak`] if there is content on both sides of them in the parent
+fn generate_text(node: &Node, content: &str, options: &PageOptions) -> String {
+    let nodedata = node.data.borrow();
+    let range = nodedata.range.clone();
+    let body = &content[range.clone()];
+    let parent = nodedata
+        .parent
+        .upgrade()
+        .map_or(range.clone(), |parent| parent.borrow().range.clone());
+    // characters around the text in its parent, markups of inline elements are skipped
+    let is_markup = |c: char| c.is_whitespace() || matches!(c, '*' | '`' | '$');
+    let mut previous = content[parent.start..range.start]
+        .trim_end_matches(is_markup)
+        .chars()
+        .last();
+    let next = content[range.end..parent.end]
+        .trim_start_matches(is_markup)
+        .chars()
+        .next();
+    let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let is_line_start = content[parent.start.max(line_start)..range.start]
+        .trim()
+        .is_empty();
+    // whitespace at the start or the end of the parent is trimmed as well
+    let is_first = nodedata.index == Some(0);
+
+    let mut text = String::new();
+    let mut is_broken = false;
+    let mut is_joined = false;
+    let segments = body.split('\n').collect::<Vec<_>>();
+    for (i, segment) in segments.iter().enumerate() {
+        let is_last = i + 1 == segments.len();
+        if i > 0 && !is_joined {
+            is_broken = true;
+        }
+        is_joined = false;
+        let mut segment = *segment;
+        if i > 0 || is_first {
+            segment = segment.trim_start();
+        }
+        if i > 0 || is_line_start {
+            segment = segment.trim_start();
+            if let Some(rest) = segment.strip_prefix('>') {
+                // a line of a single `>` breaks lines of a quote if more lines follow, unless
+                // it is followed by inline elements such as `> *line*`
+                let is_followed = next.is_some()
+                    || segments[i + 1..]
+                        .iter()
+                        .any(|segment| !segment.trim().is_empty());
+                if rest.trim().is_empty() && !is_last && is_followed {
+                    text.push_str("<br/>");
+                    is_broken = false;
+                    is_joined = true;
+                    continue;
+                }
+                segment = rest.trim_start();
+            }
+        }
+        if !is_last || next.is_none() {
+            segment = segment.trim_end();
+        }
+        let Some(first) = segment.chars().next() else {
+            continue;
+        };
+        if is_broken {
+            if let Some(previous) = previous {
+                text.push_str(line_break(previous, first, options));
+            }
+            is_broken = false;
+        }
+        text.push_str(&utils::escape_to_html(segment));
+        previous = segment.chars().last();
+    }
+    if let (true, Some(previous), Some(next)) = (is_broken, previous, next) {
+        text.push_str(line_break(previous, next, options));
+    }
+    text
+}
+
+/// A soft line break between `previous` and `next` characters
+fn line_break(
+    previous: char,
+    next: char,
+    options: &PageOptions,
+) -> &'static str {
+    match options.soft_break {
+        SoftBreak::Space if is_spaced(previous) || is_spaced(next) => " ",
+        SoftBreak::Space => "",
+        SoftBreak::Newline => "\n",
+        SoftBreak::HardBreak => "<br/>\n",
+    }
+}
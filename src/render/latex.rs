@@ -1,10 +1,11 @@
-use std::{path::Path};
+use std::{fmt, path::Path};
 
 use indoc::formatdoc;
 
 use super::{mark, RenderType};
 use crate::{
     node::{Emphasis, Node, NodeTagName},
+    options::{PageOptions, SoftBreak},
     utils,
 };
 
@@ -50,10 +51,12 @@ impl Cmd {
     pub fn append_cmd(&mut self, cmd: &Cmd) {
         self.body.push_str(&cmd.to_string())
     }
+}
 
-    pub fn to_string(&self) -> String {
+impl fmt::Display for Cmd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.name.is_empty() {
-            return self.body.to_owned();
+            return write!(f, "{}", self.body);
         }
 
         let mut content = if self.is_enclosed {
@@ -73,11 +76,15 @@ impl Cmd {
             content.push('\n');
             content.push_str(&format!(r#"\end{{{}}}"#, self.name));
         }
-        content
+        write!(f, "{content}")
     }
 }
 
-pub fn generate<S: AsRef<str>>(node: &Node, content: S) -> String {
+pub fn generate<S: AsRef<str>>(
+    node: &Node,
+    content: S,
+    options: &PageOptions,
+) -> String {
     let content = content.as_ref();
     let nodedata = node.data.borrow();
     let bodystr = &content[nodedata.range.start..nodedata.range.end];
@@ -132,14 +139,14 @@ pub fn generate<S: AsRef<str>>(node: &Node, content: S) -> String {
                 _ => Cmd::new("itemize").enclosed(),
             };
             for child in node.children().iter() {
-                texenv.append(generate(child, content).as_str());
+                texenv.append(generate(child, content, options).as_str());
             }
             texenv.to_string()
         }
         NodeTagName::ListItem => {
             let mut text = Cmd::new("item").to_string();
             for child in node.children().iter() {
-                text.push_str(generate(child, content).as_str());
+                text.push_str(generate(child, content, options).as_str());
             }
             text
         }
@@ -148,18 +155,40 @@ pub fn generate<S: AsRef<str>>(node: &Node, content: S) -> String {
             if nodedata.tag.name == NodeTagName::Para {
                 text.push('\n');
             }
-            for child in node.children().iter().filter(|x| {
-                let (start, end) =
-                    (x.data.borrow().range.start, x.data.borrow().range.end);
-                !content[start..end].trim().is_empty()
-            }) {
-                text.push_str(generate(child, content).as_str());
+            let children = node
+                .children()
+                .into_iter()
+                .filter(|x| {
+                    let (start, end) = (
+                        x.data.borrow().range.start,
+                        x.data.borrow().range.end,
+                    );
+                    !content[start..end].trim().is_empty()
+                })
+                .collect::<Vec<Node>>();
+            for (i, child) in children.iter().enumerate() {
+                let mut child_text = generate(child, content, options);
+                if options.soft_break == SoftBreak::HardBreak
+                    && child.data.borrow().tag.name == NodeTagName::Text
+                {
+                    // the line break at the end of paragraph is not a hard break
+                    let trailing = if i + 1 == children.len() {
+                        let trimmed = child_text.trim_end();
+                        let trailing = child_text[trimmed.len()..].to_owned();
+                        child_text.truncate(trimmed.len());
+                        trailing
+                    } else {
+                        "".to_owned()
+                    };
+                    child_text = child_text.replace('\n', "\\\\\n") + &trailing;
+                }
+                text.push_str(&child_text);
             }
             text.to_owned()
         }
         NodeTagName::Image => {
             let alt = node.get_attr_or("name", "image link is broken");
-            let src = node.get_attr_or("src", "");
+            let src = options.image_src(&node.get_attr_or("src", ""));
             let imgpath = Path::new(&src);
             if imgpath.exists() {
                 let mut cmd = Cmd::new("figure").enclosed().with_optarg("H");
@@ -199,12 +228,19 @@ pub fn generate<S: AsRef<str>>(node: &Node, content: S) -> String {
             };
             let mut text = String::new();
             for child in node.children().iter() {
-                text.push_str(generate(child, content).as_str());
+                text.push_str(generate(child, content, options).as_str());
             }
-            Cmd::new(name).with_posarg(text).to_string()
+            let mut heading = Cmd::new(name).with_posarg(text).to_string();
+            if let Some(id) = nodedata.tag.attrs.get("id") {
+                heading
+                    .push_str(&Cmd::new("label").with_posarg(id).to_string());
+            }
+            heading
         }
         NodeTagName::Extension => {
-            if let Some(value) = mark::generate(bodystr, RenderType::Latex) {
+            if let Some(value) =
+                mark::generate(bodystr, RenderType::Latex, options.sanitize)
+            {
                 value
             } else {
                 log::warn!("unsupported mark element: {}", bodystr);
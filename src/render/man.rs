@@ -0,0 +1,255 @@
+use super::{mark, markdown::is_spaced, RenderType};
+use crate::{
+    meta::Meta,
+    node::{Emphasis, Node, NodeTagName},
+    options::{PageOptions, SanitizePolicy, SoftBreak},
+    utils,
+};
+
+/// Render the `.TH` title line from `meta`, and a `NAME` section if there is a subtitle
+///
+/// The title is in `name(section)` form such as `ls(1)`, the section defaults to `1`.
+pub fn preamble(meta: &Meta) -> String {
+    let title = meta.title.trim();
+    let (name, section) = match title.strip_suffix(')').and_then(|title| {
+        title
+            .rsplit_once('(')
+            .filter(|(name, _)| !name.trim().is_empty())
+    }) {
+        Some((name, section)) => (name.trim(), section.trim()),
+        None => (title, "1"),
+    };
+    let mut text = format!(
+        ".TH {} {} {}\n",
+        argument(&utils::escape_to_roff(&name.to_uppercase())),
+        argument(&utils::escape_to_roff(section)),
+        argument(&utils::escape_to_roff(
+            &meta.date.format("%Y-%m-%d").to_string()
+        )),
+    );
+    if let Some(subtitle) = &meta.subtitle {
+        text.push_str(".SH NAME\n");
+        text.push_str(&escape_line(&format!(
+            "{} \\- {}",
+            utils::escape_to_roff(name),
+            utils::escape_to_roff(subtitle)
+        )));
+        text.push('\n');
+    }
+    text
+}
+
+/// Render the AST into roff source with `man(7)` macros
+///
+/// - level 1 and 2 headings are `.SH` and `.SS`, deeper headings are bold paragraphs
+/// - paragraphs are `.PP`, quotes are indented by `.RS`/`.RE`
+/// - list items are `.IP` with bullets, and their bodies are indented by `.RS`/`.RE`
+/// - code blocks are `.EX`/`.EE`, emphasis and inline code are `\fI` and `\fB`
+pub fn generate<S: AsRef<str>>(
+    node: &Node,
+    content: S,
+    options: &PageOptions,
+) -> String {
+    let mut text = String::new();
+    let renderer = Renderer {
+        content: content.as_ref(),
+        options,
+    };
+    renderer.generate_block(node, &mut text);
+    text
+}
+
+struct Renderer<'a> {
+    content: &'a str,
+    options: &'a PageOptions,
+}
+
+impl<'a> Renderer<'a> {
+    fn generate_block(&self, node: &Node, text: &mut String) {
+        let nodedata = node.data.borrow();
+        let body = &self.content[nodedata.range.clone()];
+        match nodedata.tag.name {
+            NodeTagName::Section | NodeTagName::ListBody => {
+                for child in node.children().iter() {
+                    self.generate_block(child, text);
+                }
+            }
+            NodeTagName::List => {
+                for item in node.children().iter() {
+                    for child in item.children().iter() {
+                        if child.data.borrow().tag.name == NodeTagName::ListHead
+                        {
+                            text.push_str(".IP \\(bu 2\n");
+                            text.push_str(&self.generate_inlines(child));
+                        } else {
+                            let mut body = String::new();
+                            self.generate_block(child, &mut body);
+                            if !body.is_empty() {
+                                text.push_str(".RS 2\n");
+                                text.push_str(&body);
+                                text.push_str(".RE\n");
+                            }
+                        }
+                    }
+                }
+            }
+            NodeTagName::Heading => {
+                let level = node
+                    .get_attr_or("level", "1")
+                    .parse::<usize>()
+                    .unwrap_or(1);
+                let heading = self.generate_inlines(node).replace('\n', " ");
+                let heading = heading.trim();
+                match level {
+                    1 => text.push_str(&format!(".SH {}\n", argument(heading))),
+                    2 => text.push_str(&format!(".SS {}\n", argument(heading))),
+                    _ => text.push_str(&format!(".PP\n\\fB{heading}\\fR\n")),
+                }
+            }
+            NodeTagName::Code => {
+                text.push_str(".PP\n.EX\n");
+                let code = utils::remove_indent(body);
+                for line in code.trim_end().lines() {
+                    text.push_str(&escape_line(&utils::escape_to_roff(line)));
+                    text.push('\n');
+                }
+                text.push_str(".EE\n");
+            }
+            NodeTagName::BlankLine => {}
+            _ => {
+                let inlines = self.generate_inlines(node);
+                if inlines.is_empty() {
+                    return;
+                }
+                let is_quote = nodedata.tag.name == NodeTagName::Para
+                    && body.trim_start().starts_with('>');
+                if is_quote {
+                    text.push_str(&format!(".RS\n.PP\n{inlines}.RE\n"));
+                } else {
+                    text.push_str(&format!(".PP\n{inlines}"));
+                }
+            }
+        }
+    }
+
+    /// Render inline children of `node` into text lines, each line ends with `\n`
+    fn generate_inlines(&self, node: &Node) -> String {
+        let is_quote = node.data.borrow().tag.name == NodeTagName::Para
+            && self.content[node.data.borrow().range.clone()]
+                .trim_start()
+                .starts_with('>');
+        let mut lines: Vec<String> = vec![String::new()];
+        for child in node.children().iter() {
+            let nodedata = child.data.borrow();
+            let body = &self.content[nodedata.range.clone()];
+            if nodedata.tag.name != NodeTagName::Text {
+                lines
+                    .last_mut()
+                    .unwrap()
+                    .push_str(&self.generate_inline(child));
+                continue;
+            }
+            for (i, line) in body.split('\n').enumerate() {
+                if i > 0 {
+                    lines.push(String::new());
+                }
+                let current = lines.last_mut().unwrap();
+                let line = if is_quote && current.is_empty() {
+                    let trimmed = line.trim_start();
+                    trimmed.strip_prefix('>').unwrap_or(trimmed)
+                } else {
+                    line
+                };
+                current.push_str(&utils::escape_to_roff(line));
+            }
+        }
+
+        let mut text = String::new();
+        for line in lines.iter().map(|line| line.trim()) {
+            if line.is_empty() {
+                continue;
+            }
+            if let (Some(previous), Some(first)) =
+                (text.chars().last(), line.chars().next())
+            {
+                match self.options.soft_break {
+                    SoftBreak::Space => {
+                        if is_spaced(previous) || is_spaced(first) {
+                            text.push(' ');
+                        }
+                    }
+                    SoftBreak::Newline => text.push('\n'),
+                    SoftBreak::HardBreak => text.push_str("\n.br\n"),
+                }
+            }
+            if text.is_empty() || text.ends_with('\n') {
+                text.push_str(&escape_line(line));
+            } else {
+                text.push_str(line);
+            }
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+
+    fn generate_inline(&self, node: &Node) -> String {
+        let nodedata = node.data.borrow();
+        let body = &self.content[nodedata.range.clone()];
+        match nodedata.tag.name {
+            NodeTagName::Code => {
+                let code = body.trim_matches('`').trim();
+                format!("\\fB{}\\fR", utils::escape_to_roff(code))
+            }
+            NodeTagName::Math => {
+                let tex = body.trim_matches('$').trim();
+                format!("\\fI{}\\fR", utils::escape_to_roff(tex))
+            }
+            NodeTagName::Emphasis(t) => {
+                let body = utils::escape_to_roff(body.trim_matches('*'));
+                match t {
+                    Emphasis::Italics => format!("\\fI{body}\\fR"),
+                    Emphasis::Bold => format!("\\fB{body}\\fR"),
+                }
+            }
+            NodeTagName::Link => {
+                let url = utils::escape_to_roff(&node.get_attr_or("href", ""));
+                let name = utils::escape_to_roff(&node.get_attr_or("name", ""));
+                if name.is_empty() || name == url {
+                    format!("\\(la{url}\\(ra")
+                } else {
+                    format!("{name} \\(la{url}\\(ra")
+                }
+            }
+            NodeTagName::Image => {
+                let alt = node.get_attr_or("name", "");
+                format!("[image: {}]", utils::escape_to_roff(&alt))
+            }
+            NodeTagName::Extension => {
+                let value = mark::generate(
+                    body,
+                    RenderType::Text,
+                    SanitizePolicy::None,
+                )
+                .unwrap_or_else(|| body.to_owned());
+                utils::escape_to_roff(value.trim())
+            }
+            _ => utils::escape_to_roff(body),
+        }
+    }
+}
+
+/// Protect a text line starting with `.` or `'` from being parsed as a request
+fn escape_line(line: &str) -> String {
+    if line.starts_with('.') || line.starts_with('\'') {
+        format!("\\&{line}")
+    } else {
+        line.to_owned()
+    }
+}
+
+/// A quoted macro argument from escaped `text`
+fn argument(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
@@ -1,17 +1,36 @@
 use super::RenderType;
-use crate::{token::Mark, utils};
+use crate::{options::SanitizePolicy, token::Mark, utils};
 
-pub fn generate<S: AsRef<str>>(content: S, typ: RenderType) -> Option<String> {
+/// Marks which are rendered specially, the value of other marks is rendered as plain text
+pub(crate) const SUPPORTED_MARKS: &[&str] = &["char", "emoji", "kbd", "note"];
+
+/// Render mark `content`, raw text is escaped in html unless `sanitize` is [`SanitizePolicy::None`]
+pub fn generate<S: AsRef<str>>(
+    content: S,
+    typ: RenderType,
+    sanitize: SanitizePolicy,
+) -> Option<String> {
     let content = content.as_ref();
+    let escape = |text: &str| {
+        if typ == RenderType::Html && sanitize != SanitizePolicy::None {
+            utils::escape_to_html(text)
+        } else {
+            text.to_owned()
+        }
+    };
     if let Some(mark) = Mark::new_from_str(content) {
         match mark.name.as_str() {
             "char" => {
                 if let Some(c) = mark.value.chars().next() {
-                    if typ == RenderType::Html {
-                        return Some(utils::escape_to_html(&c.to_string()));
-                    } else {
-                        return Some(utils::escape_to_tex(&c.to_string()));
-                    }
+                    return Some(match typ {
+                        RenderType::Html => {
+                            utils::escape_to_html(&c.to_string())
+                        }
+                        RenderType::Latex => {
+                            utils::escape_to_tex(&c.to_string())
+                        }
+                        RenderType::Text => c.to_string(),
+                    });
                 }
                 return Some("".to_string());
             }
@@ -23,11 +42,13 @@ pub fn generate<S: AsRef<str>>(content: S, typ: RenderType) -> Option<String> {
                     if let Some(emoji) = gh_emoji::get(name) {
                         emojis.push_str(emoji);
                     } else {
-                        emojis.push_str(&format!(" {} ", name));
+                        emojis.push_str(&format!(" {} ", escape(name)));
                     }
                 }
                 return Some(emojis);
             }
+            // speaker notes are only shown in slides
+            "note" => return Some("".to_owned()),
             "kbd" => {
                 let value = mark
                     .value
@@ -40,7 +61,7 @@ pub fn generate<S: AsRef<str>>(content: S, typ: RenderType) -> Option<String> {
                             key.trim()
                         };
                         if typ == RenderType::Html {
-                            format!("<kbd>{}</kbd>", key)
+                            format!("<kbd>{}</kbd>", escape(key))
                         } else {
                             key.to_owned()
                         }
@@ -49,7 +70,7 @@ pub fn generate<S: AsRef<str>>(content: S, typ: RenderType) -> Option<String> {
                 return Some(value.join("+").to_string());
             }
             _ => {
-                return Some(mark.value);
+                return Some(escape(&mark.value));
             }
         }
     }
@@ -0,0 +1,340 @@
+use super::push_blank_line;
+use crate::{
+    node::{Node, NodeTagName},
+    token::{Heading, List},
+    utils,
+};
+
+/// Serialize the AST back into canonical ConciseMark source
+///
+/// - blocks in a section are separated by exactly one blank line
+/// - list items use [`List::LIST_MARK`] and nested content is indented by [`List::INDENT_MARK`]
+/// - heading markers are normalized to `#` ~ `######` followed by one space
+/// - links and images are generated from node attributes, so changes made by hooks are kept
+///
+/// If `width` is given, paragraphs and list heads are wrapped to fit in `width` columns, see
+/// [`wrap_inlines`].
+pub fn generate<S: AsRef<str>>(
+    node: &Node,
+    content: S,
+    width: Option<usize>,
+) -> String {
+    let mut text = String::new();
+    let serializer = Serializer {
+        content: content.as_ref(),
+        width,
+    };
+    serializer.generate_block(node, 0, &mut text);
+    // the trailing space of an empty list item `- ` is kept
+    let text = text.trim_end_matches('\n');
+    if text.is_empty() {
+        return "".to_owned();
+    }
+    format!("{text}\n")
+}
+
+struct Serializer<'a> {
+    content: &'a str,
+    width: Option<usize>,
+}
+
+impl<'a> Serializer<'a> {
+    fn generate_block(&self, node: &Node, indent: usize, text: &mut String) {
+        let content = self.content;
+        let nodedata = node.data.borrow();
+        let indentstr = " ".repeat(indent);
+        match nodedata.tag.name {
+            NodeTagName::Section => {
+                for child in node.children().iter() {
+                    if child.data.borrow().tag.name == NodeTagName::BlankLine {
+                        continue;
+                    }
+                    if !text.is_empty() {
+                        push_blank_line(text);
+                    }
+                    self.generate_block(child, indent, text);
+                }
+            }
+            NodeTagName::ListBody => {
+                // blank lines in list body are kept since they decide whether the list is compact
+                for child in node.children().iter() {
+                    if child.data.borrow().tag.name == NodeTagName::BlankLine {
+                        push_blank_line(text);
+                    } else {
+                        self.generate_block(child, indent, text);
+                    }
+                }
+            }
+            NodeTagName::List => {
+                for item in node.children().iter() {
+                    for child in item.children().iter() {
+                        let name = child.data.borrow().tag.name;
+                        if name == NodeTagName::ListHead {
+                            let head = self.generate_inlines(
+                                child,
+                                indent + List::LIST_MARK.len(),
+                            );
+                            text.push_str(&format!(
+                                "{indentstr}{}{head}\n",
+                                List::LIST_MARK
+                            ));
+                        } else {
+                            self.generate_block(
+                                child,
+                                indent + List::INDENT_MARK.len(),
+                                text,
+                            );
+                        }
+                    }
+                }
+            }
+            NodeTagName::Heading => {
+                let level = node
+                    .get_attr_or("level", "1")
+                    .parse::<usize>()
+                    .unwrap_or(1)
+                    .clamp(1, 6);
+                let heading = self.generate_inlines(node, 0);
+                let heading = format!("{} {heading}", "#".repeat(level));
+                text.push_str(&format!("{}\n", heading.trim_end()));
+            }
+            NodeTagName::Para => {
+                let para = self.generate_inlines(node, indent);
+                // one space is not a list continuation or code indentation, but it stops the
+                // paragraph from being parsed as a heading or list
+                let lead = if is_block_start(&para) { " " } else { "" };
+                text.push_str(&format!("{indentstr}{lead}{para}\n"));
+            }
+            NodeTagName::Code => {
+                let body = &content[nodedata.range.start..nodedata.range.end];
+                let code_indentstr =
+                    format!("{indentstr}{}", List::INDENT_MARK);
+                for line in utils::remove_indent(body).trim_end().lines() {
+                    if line.trim().is_empty() {
+                        text.push('\n');
+                    } else {
+                        text.push_str(&format!("{code_indentstr}{line}\n"));
+                    }
+                }
+            }
+            NodeTagName::BlankLine => push_blank_line(text),
+            _ => {
+                let inline = self.generate_inline(node);
+                text.push_str(&format!("{indentstr}{}\n", inline.trim()));
+            }
+        }
+    }
+
+    /// Serialize inline children of `node`, continued lines are indented by `indent` spaces
+    fn generate_inlines(&self, node: &Node, indent: usize) -> String {
+        let indentstr = " ".repeat(indent);
+        let wrappable = match node.data.borrow().tag.name {
+            NodeTagName::Heading => false,
+            NodeTagName::Para => !self.text(node).trim_start().starts_with('>'),
+            _ => true,
+        };
+        if let Some(width) = self.width.filter(|_| wrappable) {
+            let width = width.saturating_sub(indent);
+            if let Some(lines) = self.wrap_inlines(node, width) {
+                return lines.join(&format!("\n{indentstr}"));
+            }
+        }
+        let inlines = node
+            .children()
+            .iter()
+            .map(|child| self.generate_inline(child))
+            .collect::<String>();
+        inlines
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<&str>>()
+            .join(&format!("\n{indentstr}"))
+    }
+
+    /// Wrap inline children of `node` into lines whose display width are not greater than `width`
+    /// if possible
+    ///
+    /// Math, code, links and marks are never broken. Line breaks between two east asian characters
+    /// are joined without whitespace when rendering, while other line breaks are joined by a space,
+    /// so we only break lines where the rendered text is not changed. `None` is returned if an inline
+    /// element itself contains line breaks.
+    fn wrap_inlines(&self, node: &Node, width: usize) -> Option<Vec<String>> {
+        // Unbreakable words with a flag telling whether there is whitespace before it
+        let mut words: Vec<(String, bool)> = vec![];
+        // Whether there is whitespace (and line break) after the last word
+        let (mut spaced, mut newline) = (false, false);
+        for child in node.children().iter() {
+            if child.data.borrow().tag.name != NodeTagName::Text {
+                let inline = self.generate_inline(child);
+                if inline.contains('\n') {
+                    return None;
+                }
+                let first = inline.chars().next().unwrap_or(' ');
+                let word_spaced =
+                    is_word_spaced(&words, first, spaced, newline);
+                push_word(&mut words, inline, word_spaced);
+                (spaced, newline) = (false, false);
+                continue;
+            }
+            let mut word = String::new();
+            let mut word_spaced = false;
+            for ch in self.text(child).chars() {
+                if ch.is_whitespace() {
+                    if !word.is_empty() {
+                        push_word(&mut words, word, word_spaced);
+                        word = String::new();
+                    }
+                    spaced = true;
+                    newline = newline || ch == '\n';
+                    continue;
+                }
+                match word.chars().last() {
+                    None => {
+                        word_spaced =
+                            is_word_spaced(&words, ch, spaced, newline);
+                    }
+                    Some(previous)
+                        if !is_spaced(previous) && !is_spaced(ch) =>
+                    {
+                        push_word(&mut words, word, word_spaced);
+                        word = String::new();
+                        word_spaced = false;
+                    }
+                    _ => {}
+                }
+                word.push(ch);
+                (spaced, newline) = (false, false);
+            }
+            if !word.is_empty() {
+                push_word(&mut words, word, word_spaced);
+            }
+        }
+
+        let mut lines: Vec<String> = vec![];
+        let mut line = String::new();
+        for (word, spaced) in words {
+            let previous = line.chars().last();
+            let first = word.chars().next().unwrap_or(' ');
+            let breakable = match previous {
+                Some(previous) => {
+                    spaced == (is_spaced(previous) || is_spaced(first))
+                        && first != '>'
+                        && !is_closing_punctuation(first)
+                        && !is_block_start(&word)
+                }
+                None => false,
+            };
+            let sep = if spaced { " " } else { "" };
+            if breakable
+                && utils::display_width(&line)
+                    + sep.len()
+                    + utils::display_width(&word)
+                    > width
+            {
+                lines.push(line);
+                line = word;
+            } else {
+                if previous.is_some() {
+                    line.push_str(sep);
+                }
+                line.push_str(&word);
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        Some(lines)
+    }
+
+    fn generate_inline(&self, node: &Node) -> String {
+        let nodedata = node.data.borrow();
+        match nodedata.tag.name {
+            NodeTagName::Link => {
+                let href = node.get_attr_or("href", "");
+                let name = node.get_attr_or("name", "");
+                format!("[{name}]({href})")
+            }
+            NodeTagName::Image => {
+                let src = node.get_attr_or("src", "");
+                let name = node.get_attr_or("name", "");
+                format!("![{name}]({src})")
+            }
+            _ => self.text(node).to_owned(),
+        }
+    }
+
+    fn text(&self, node: &Node) -> &'a str {
+        let nodedata = node.data.borrow();
+        &self.content[nodedata.range.start..nodedata.range.end]
+    }
+}
+
+/// Whether a paragraph starting with `text` would start another block if it were not indented,
+/// lines are never wrapped before such words
+///
+/// A digit followed by `.` is not an ordered list yet, it is kept indented so that paragraphs
+/// keep their meaning if ordered lists are supported. A paragraph starting with `>` is a
+/// blockquote whether it is indented or not.
+fn is_block_start(text: &str) -> bool {
+    let number = text.trim_start_matches(|ch: char| ch.is_ascii_digit());
+    text.starts_with(Heading::MARK)
+        || text.starts_with(List::LIST_MARK)
+        || text == List::LIST_MARK.trim_end()
+        || (number.len() < text.len() && number.starts_with('.'))
+}
+
+/// Whether a word starting with `first` is separated from the last word by a space when rendering
+fn is_word_spaced(
+    words: &[(String, bool)],
+    first: char,
+    spaced: bool,
+    newline: bool,
+) -> bool {
+    match words.last().and_then(|(word, _)| word.chars().last()) {
+        // a line break between two east asian characters is not a space
+        Some(previous) if spaced && newline => {
+            is_spaced(previous) || is_spaced(first)
+        }
+        _ => spaced,
+    }
+}
+
+/// Append `word` to `words`, it is glued to the last word if there is no whitespace between them
+/// unless they are both east asian characters
+fn push_word(words: &mut Vec<(String, bool)>, word: String, spaced: bool) {
+    if let Some((last, _)) = words.last_mut() {
+        let previous = last.chars().last().unwrap_or(' ');
+        let first = word.chars().next().unwrap_or(' ');
+        if !spaced && (is_spaced(previous) || is_spaced(first)) {
+            last.push_str(&word);
+            return;
+        }
+    }
+    words.push((word, spaced));
+}
+
+/// Whether the character is joined with a space when it is at the edge of a line break, this must
+/// keep the same with html renderer
+pub(crate) fn is_spaced(ch: char) -> bool {
+    ch.is_ascii_alphanumeric()
+        || ch.is_ascii_punctuation()
+        || ch.is_ascii_whitespace()
+}
+
+/// Fullwidth punctuation which should not be put at the start of a line
+pub(super) fn is_closing_punctuation(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{3001}'
+            | '\u{3002}'
+            | '\u{FF01}'
+            | '\u{FF09}'
+            | '\u{FF0C}'
+            | '\u{FF1A}'
+            | '\u{FF1B}'
+            | '\u{FF1F}'
+            | '\u{300D}'
+            | '\u{300F}'
+    )
+}
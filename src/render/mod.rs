@@ -1,9 +1,23 @@
+pub mod ansi;
 pub mod html;
 pub mod latex;
+pub mod man;
 pub mod mark;
+pub mod markdown;
+pub mod slides;
+pub mod text;
+pub mod typst;
 
 #[derive(Debug, PartialEq)]
 pub enum RenderType {
     Html,
     Latex,
+    Text,
+}
+
+/// Make sure `text` ends with one blank line
+pub(crate) fn push_blank_line(text: &mut String) {
+    while !text.is_empty() && !text.ends_with("\n\n") {
+        text.push('\n');
+    }
 }
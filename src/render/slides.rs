@@ -0,0 +1,273 @@
+use super::latex::{self, Cmd};
+use crate::{
+    meta::Meta,
+    node::{self, Node, NodeTagName},
+    options::PageOptions,
+    token::Mark,
+    utils,
+};
+
+/// A slide of the deck
+pub struct Frame {
+    /// The level-1 or level-2 heading starting the frame, `None` for the content before the
+    /// first heading
+    pub heading: Option<Node>,
+    /// Blocks of the frame except the heading and blocks containing only notes
+    pub blocks: Vec<Node>,
+    /// Values of `@note{}` marks in the frame
+    pub notes: Vec<String>,
+}
+
+impl Frame {
+    fn level(&self) -> Option<usize> {
+        self.heading.as_ref().map(heading_level)
+    }
+}
+
+/// Split the AST into frames, level-1 and level-2 headings start new frames
+pub fn split<S: AsRef<str>>(node: &Node, content: S) -> Vec<Frame> {
+    let content = content.as_ref();
+    let mut frames: Vec<Frame> = vec![];
+    for child in node.children() {
+        let name = child.data.borrow().tag.name;
+        if name == NodeTagName::BlankLine {
+            continue;
+        }
+        if name == NodeTagName::Heading && heading_level(&child) <= 2 {
+            frames.push(Frame {
+                heading: Some(child),
+                blocks: vec![],
+                notes: vec![],
+            });
+            continue;
+        }
+        if frames.is_empty() {
+            frames.push(Frame {
+                heading: None,
+                blocks: vec![],
+                notes: vec![],
+            });
+        }
+        let frame = frames.last_mut().unwrap();
+        let notes = notes(&child, content);
+        if notes.is_empty() || !is_note_only(&child, content) {
+            frame.blocks.push(child);
+        }
+        frame.notes.extend(notes);
+    }
+    frames
+}
+
+/// Render a Beamer document, [`Meta`] is used for the title frame
+///
+/// Level-1 headings start sections as well, and notes are put into `\note` commands.
+pub fn beamer<S: AsRef<str>>(
+    node: &Node,
+    content: S,
+    meta: Option<&Meta>,
+    options: &PageOptions,
+) -> String {
+    let content = content.as_ref();
+    let mut page = include_str!("../../assets/slides/beamer.tex").to_owned();
+    let mut document = Cmd::new("document").enclosed();
+    if let Some(meta) = meta {
+        document.append_cmd(&Cmd::new("title").with_posarg(&meta.title));
+        if let Some(subtitle) = &meta.subtitle {
+            document.append_cmd(&Cmd::new("subtitle").with_posarg(subtitle));
+        }
+        if let Some(authors) = &meta.authors {
+            document.append_cmd(
+                &Cmd::new("author").with_posarg(authors.join(", ")),
+            );
+        }
+        document.append_cmd(
+            &Cmd::new("date")
+                .with_posarg(meta.date.format("%Y-%m-%d").to_string()),
+        );
+        let mut frame = Cmd::new("frame").enclosed();
+        frame.append_cmd(&Cmd::new("titlepage"));
+        document.append(format!("{frame}\n"));
+    }
+    for frame in split(node, content) {
+        let mut cmd = Cmd::new("frame").enclosed();
+        let has_code = frame.blocks.iter().any(|block| {
+            block.data.borrow().tag.name == NodeTagName::Code
+                || !node::find_nodes_by_tag(block, NodeTagName::Code).is_empty()
+        });
+        if has_code {
+            cmd = cmd.with_optarg("fragile");
+        }
+        if let Some(heading) = &frame.heading {
+            let title = heading
+                .children()
+                .iter()
+                .map(|child| latex::generate(child, content, options))
+                .collect::<String>();
+            if frame.level() == Some(1) {
+                document.append_cmd(&Cmd::new("section").with_posarg(&title));
+            }
+            cmd = cmd.with_posarg(title);
+        }
+        for block in &frame.blocks {
+            if block.data.borrow().tag.name == NodeTagName::Heading {
+                // sections can not be nested in frames
+                let text = block
+                    .children()
+                    .iter()
+                    .map(|child| latex::generate(child, content, options))
+                    .collect::<String>();
+                cmd.append(format!(
+                    "\n{}\n",
+                    Cmd::new("textbf").with_posarg(text).to_string().trim()
+                ));
+            } else {
+                cmd.append(latex::generate(block, content, options));
+            }
+        }
+        for note in &frame.notes {
+            cmd.append(format!(
+                "\n{}",
+                Cmd::new("note").with_posarg(utils::escape_to_tex(note))
+            ));
+        }
+        document.append(format!("{cmd}\n"));
+    }
+    page.push_str(&document.to_string());
+    page.push('\n');
+    page
+}
+
+/// Render a self-contained HTML page using the markup of [reveal.js](https://revealjs.com)
+///
+/// Each frame is a `section` and notes are put into `aside class="notes"`. A bundled script shows
+/// one slide at a time, it moves between slides with arrow keys and toggles notes with `n`.
+pub fn html<S: AsRef<str>>(
+    node: &Node,
+    content: S,
+    meta: Option<&Meta>,
+    options: &PageOptions,
+) -> String {
+    let content = content.as_ref();
+    let render = |node: &Node| {
+        super::html::generate(
+            node,
+            content,
+            options,
+            Some(&|_: &Node| -> Option<String> { None }),
+        )
+    };
+    let frames = split(node, content);
+    let mut slides = String::new();
+    if let Some(meta) = meta {
+        slides.push_str(&format!(
+            "<section class=\"title-slide\">\n<h1>{}</h1>\n",
+            utils::escape_to_html(&meta.title)
+        ));
+        if let Some(subtitle) = &meta.subtitle {
+            slides.push_str(&format!(
+                "<p class=\"subtitle\">{}</p>\n",
+                utils::escape_to_html(subtitle)
+            ));
+        }
+        if let Some(authors) = &meta.authors {
+            slides.push_str(&format!(
+                "<p class=\"author\">{}</p>\n",
+                utils::escape_to_html(&authors.join(", "))
+            ));
+        }
+        slides.push_str(&format!(
+            "<p class=\"date\">{}</p>\n</section>\n",
+            meta.date.format("%Y-%m-%d")
+        ));
+    }
+    for frame in &frames {
+        match frame.level() {
+            Some(level) => {
+                slides.push_str(&format!("<section data-level=\"{level}\">\n"))
+            }
+            None => slides.push_str("<section>\n"),
+        }
+        for block in frame.heading.iter().chain(&frame.blocks) {
+            slides.push_str(&render(block));
+            slides.push('\n');
+        }
+        if !frame.notes.is_empty() {
+            slides.push_str("<aside class=\"notes\">\n");
+            for note in &frame.notes {
+                slides.push_str(&format!(
+                    "<p>{}</p>\n",
+                    utils::escape_to_html(note)
+                ));
+            }
+            slides.push_str("</aside>\n");
+        }
+        slides.push_str("</section>\n");
+    }
+
+    let title = meta
+        .map(|meta| meta.title.clone())
+        .or_else(|| {
+            frames
+                .iter()
+                .find_map(|frame| frame.heading.as_ref())
+                .map(|heading| node::inline_text(heading, content))
+        })
+        .unwrap_or_else(|| "Slides".to_owned());
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{}</title>
+<style>
+{}</style>
+</head>
+<body>
+<div class="reveal">
+<div class="slides">
+{slides}</div>
+</div>
+<script>
+{}</script>
+<script>
+{}</script>
+</body>
+</html>
+"#,
+        utils::escape_to_html(&title),
+        include_str!("../../assets/slides/slides.css"),
+        include_str!("../../assets/serve/math.js"),
+        include_str!("../../assets/slides/slides.js"),
+    )
+}
+
+fn heading_level(node: &Node) -> usize {
+    node.get_attr_or("level", "1").parse().unwrap_or(1)
+}
+
+/// Values of `@note{}` marks in `node`
+fn notes(node: &Node, content: &str) -> Vec<String> {
+    node::find_nodes_by_tag(node, NodeTagName::Extension)
+        .iter()
+        .filter_map(|mark| {
+            Mark::new_from_str(&content[mark.data.borrow().range.clone()])
+        })
+        .filter(|mark| mark.name == "note")
+        .map(|mark| mark.value)
+        .collect()
+}
+
+/// Whether `node` contains nothing but notes and whitespace
+fn is_note_only(node: &Node, content: &str) -> bool {
+    node.children().iter().all(|child| {
+        let nodedata = child.data.borrow();
+        let text = &content[nodedata.range.clone()];
+        match nodedata.tag.name {
+            NodeTagName::Extension => {
+                Mark::new_from_str(text).is_some_and(|mark| mark.name == "note")
+            }
+            _ => text.trim().is_empty(),
+        }
+    })
+}
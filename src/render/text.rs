@@ -0,0 +1,181 @@
+use super::{mark, markdown::is_spaced, push_blank_line, RenderType};
+use crate::{
+    node::{Node, NodeTagName},
+    options::{PageOptions, SanitizePolicy, SoftBreak},
+    token::List,
+    utils,
+};
+
+/// Render the AST into plain text
+///
+/// - blocks are separated by one blank line, list items are prefixed with [`List::LIST_MARK`]
+///   and nested content is indented by two spaces
+/// - code blocks are indented by [`List::INDENT_MARK`], quotes are prefixed with `> `
+/// - emphasis markers, link and image syntax are stripped, math is kept as raw TeX, and marks
+///   are rendered as text by [`mark::generate`]
+pub fn generate<S: AsRef<str>>(
+    node: &Node,
+    content: S,
+    options: &PageOptions,
+) -> String {
+    let mut text = String::new();
+    let renderer = Renderer {
+        content: content.as_ref(),
+        options,
+    };
+    renderer.generate_block(node, "", &mut text);
+    let text = text.trim_end();
+    if text.is_empty() {
+        return "".to_owned();
+    }
+    format!("{text}\n")
+}
+
+struct Renderer<'a> {
+    content: &'a str,
+    options: &'a PageOptions,
+}
+
+impl<'a> Renderer<'a> {
+    fn generate_block(&self, node: &Node, indent: &str, text: &mut String) {
+        let nodedata = node.data.borrow();
+        match nodedata.tag.name {
+            NodeTagName::Section | NodeTagName::ListBody => {
+                for child in node.children().iter() {
+                    if child.data.borrow().tag.name == NodeTagName::BlankLine {
+                        continue;
+                    }
+                    if !text.is_empty() {
+                        push_blank_line(text);
+                    }
+                    self.generate_block(child, indent, text);
+                }
+            }
+            NodeTagName::List => {
+                let nested = format!("{indent}  ");
+                for item in node.children().iter() {
+                    for child in item.children().iter() {
+                        if child.data.borrow().tag.name == NodeTagName::ListHead
+                        {
+                            let head = self.generate_inlines(child);
+                            text.push_str(&format!(
+                                "{indent}{}{}\n",
+                                List::LIST_MARK,
+                                head.replace('\n', &format!("\n{nested}"))
+                            ));
+                        } else {
+                            // list body is put right after the list head
+                            let mut body = String::new();
+                            self.generate_block(child, &nested, &mut body);
+                            if !body.trim().is_empty() {
+                                push_blank_line(text);
+                                text.push_str(&body);
+                                push_blank_line(text);
+                            }
+                        }
+                    }
+                }
+            }
+            NodeTagName::Code => {
+                let body = &self.content[nodedata.range.clone()];
+                for line in utils::remove_indent(body).trim_end().lines() {
+                    if line.trim().is_empty() {
+                        text.push('\n');
+                    } else {
+                        text.push_str(&format!(
+                            "{indent}{}{line}\n",
+                            List::INDENT_MARK
+                        ));
+                    }
+                }
+            }
+            NodeTagName::BlankLine => {}
+            _ => {
+                let inlines = self.generate_inlines(node);
+                for line in inlines.lines() {
+                    text.push_str(&format!("{indent}{line}\n"));
+                }
+            }
+        }
+    }
+
+    /// Render inline children of `node`, lines are joined according to [`SoftBreak`]
+    fn generate_inlines(&self, node: &Node) -> String {
+        let mut inlines = node
+            .children()
+            .iter()
+            .map(|child| self.generate_inline(child))
+            .collect::<String>();
+        let is_quote = node.data.borrow().tag.name == NodeTagName::Para
+            && inlines.trim_start().starts_with('>');
+        if is_quote {
+            inlines = inlines
+                .lines()
+                .map(|line| {
+                    let line = line.trim_start();
+                    line.strip_prefix('>').unwrap_or(line)
+                })
+                .collect::<Vec<&str>>()
+                .join("\n");
+        }
+        let lines = inlines
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<&str>>();
+        let mut text = String::new();
+        for line in lines {
+            if let (Some(previous), Some(first)) =
+                (text.chars().last(), line.chars().next())
+            {
+                match self.options.soft_break {
+                    SoftBreak::Space => {
+                        if is_spaced(previous) || is_spaced(first) {
+                            text.push(' ');
+                        }
+                    }
+                    SoftBreak::Newline | SoftBreak::HardBreak => {
+                        text.push('\n')
+                    }
+                }
+            }
+            text.push_str(line);
+        }
+        if is_quote {
+            text = text
+                .lines()
+                .map(|line| format!("> {line}"))
+                .collect::<Vec<String>>()
+                .join("\n");
+        }
+        text
+    }
+
+    fn generate_inline(&self, node: &Node) -> String {
+        let nodedata = node.data.borrow();
+        let body = &self.content[nodedata.range.clone()];
+        match nodedata.tag.name {
+            NodeTagName::Code => body.trim_matches('`').trim().to_owned(),
+            NodeTagName::Math => body.trim_matches('$').trim().to_owned(),
+            NodeTagName::Emphasis(_) => body.trim_matches('*').to_owned(),
+            NodeTagName::Link => {
+                let url = node.get_attr_or("href", "");
+                let name = node.get_attr_or("name", "");
+                if name.is_empty() {
+                    url
+                } else if self.options.text_urls && name != url {
+                    format!("{name} ({url})")
+                } else {
+                    name
+                }
+            }
+            NodeTagName::Image => node.get_attr_or("name", ""),
+            NodeTagName::Extension => {
+                mark::generate(body, RenderType::Text, SanitizePolicy::None)
+                    .map(|text| text.trim().to_owned())
+                    .unwrap_or_else(|| body.to_owned())
+            }
+            _ => body.to_owned(),
+        }
+    }
+}
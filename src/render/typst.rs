@@ -0,0 +1,621 @@
+use std::path::Path;
+
+use super::{mark, markdown::is_spaced, push_blank_line, RenderType};
+use crate::{
+    meta::Meta,
+    node::{Emphasis, Node, NodeTagName},
+    options::{PageOptions, SanitizePolicy, SoftBreak},
+    utils,
+};
+
+/// Render the document preamble from `meta`: document metadata and a centered title block
+pub fn preamble(meta: &Meta) -> String {
+    let mut text = String::new();
+    let authors = meta.authors.clone().unwrap_or_default();
+    text.push_str(&format!(
+        "#set document(title: {}, author: ({}), date: datetime(year: {}, month: {}, day: {}))\n",
+        string(&meta.title),
+        authors
+            .iter()
+            .map(|author| format!("{},", string(author)))
+            .collect::<String>(),
+        meta.date.format("%Y"),
+        meta.date.format("%-m"),
+        meta.date.format("%-d"),
+    ));
+    text.push_str("#align(center)[\n");
+    text.push_str(&format!(
+        "  #text(size: 2em, weight: \"bold\")[{}]\n",
+        utils::escape_to_typst(&meta.title)
+    ));
+    if let Some(subtitle) = &meta.subtitle {
+        text.push_str(&format!(
+            "\n  #text(size: 1.2em)[{}]\n",
+            utils::escape_to_typst(subtitle)
+        ));
+    }
+    if !authors.is_empty() {
+        text.push_str(&format!(
+            "\n  {}\n",
+            utils::escape_to_typst(&authors.join(", "))
+        ));
+    }
+    text.push_str(&format!("\n  {}\n", meta.date.format("%Y-%m-%d")));
+    text.push_str("]\n\n");
+    text
+}
+
+/// Render the AST into Typst markup
+///
+/// Code blocks are fenced with the language in their `lang` attribute if any (it can be set by
+/// [`Page::transform`](crate::Page::transform)), math is translated into Typst math by
+/// [`tex_to_typst`], or kept as a raw LaTeX block if it uses unsupported commands.
+pub fn generate<S: AsRef<str>>(
+    node: &Node,
+    content: S,
+    options: &PageOptions,
+) -> String {
+    let mut text = String::new();
+    let renderer = Renderer {
+        content: content.as_ref(),
+        options,
+    };
+    renderer.generate_block(node, "", &mut text);
+    let text = text.trim_end();
+    if text.is_empty() {
+        return "".to_owned();
+    }
+    format!("{text}\n")
+}
+
+struct Renderer<'a> {
+    content: &'a str,
+    options: &'a PageOptions,
+}
+
+impl<'a> Renderer<'a> {
+    fn generate_block(&self, node: &Node, indent: &str, text: &mut String) {
+        let nodedata = node.data.borrow();
+        let body = &self.content[nodedata.range.clone()];
+        match nodedata.tag.name {
+            NodeTagName::Section | NodeTagName::ListBody => {
+                for child in node.children().iter() {
+                    if child.data.borrow().tag.name == NodeTagName::BlankLine {
+                        continue;
+                    }
+                    if !text.is_empty() {
+                        push_blank_line(text);
+                    }
+                    self.generate_block(child, indent, text);
+                }
+            }
+            NodeTagName::List => {
+                let nested = format!("{indent}  ");
+                for item in node.children().iter() {
+                    for child in item.children().iter() {
+                        if child.data.borrow().tag.name == NodeTagName::ListHead
+                        {
+                            let head = self.generate_inlines(child);
+                            text.push_str(&format!(
+                                "{indent}- {}\n",
+                                head.replace('\n', &format!("\n{nested}"))
+                            ));
+                        } else {
+                            let mut body = String::new();
+                            self.generate_block(child, &nested, &mut body);
+                            if !body.trim().is_empty() {
+                                push_blank_line(text);
+                                text.push_str(&body);
+                                push_blank_line(text);
+                            }
+                        }
+                    }
+                }
+            }
+            NodeTagName::Heading => {
+                let level = node
+                    .get_attr_or("level", "1")
+                    .parse::<usize>()
+                    .unwrap_or(1)
+                    .clamp(1, 6);
+                let mut heading = format!(
+                    "{indent}{} {}",
+                    "=".repeat(level),
+                    self.generate_inlines(node).replace('\n', " ")
+                );
+                if let Some(id) = nodedata.tag.attrs.get("id") {
+                    heading.push_str(&format!(" <{id}>"));
+                }
+                text.push_str(&heading);
+                text.push('\n');
+            }
+            NodeTagName::Code => {
+                let code = utils::remove_indent(body);
+                let code = code.trim_end();
+                let fence = fence(code);
+                let lang = node.get_attr_or("lang", "");
+                text.push_str(&format!("{indent}{fence}{lang}\n"));
+                for line in code.lines() {
+                    if line.trim().is_empty() {
+                        text.push('\n');
+                    } else {
+                        text.push_str(&format!("{indent}{line}\n"));
+                    }
+                }
+                text.push_str(&format!("{indent}{fence}\n"));
+            }
+            NodeTagName::BlankLine => {}
+            _ => {
+                let is_quote = nodedata.tag.name == NodeTagName::Para
+                    && body.trim_start().starts_with('>');
+                let mut inlines = self.generate_inlines(node);
+                if is_quote {
+                    inlines = format!("#quote(block: true)[{inlines}]");
+                }
+                for line in inlines.lines() {
+                    text.push_str(&format!("{indent}{line}\n"));
+                }
+            }
+        }
+    }
+
+    /// Render inline children of `node`, lines are joined according to [`SoftBreak`]
+    fn generate_inlines(&self, node: &Node) -> String {
+        let is_quote = node.data.borrow().tag.name == NodeTagName::Para
+            && self.content[node.data.borrow().range.clone()]
+                .trim_start()
+                .starts_with('>');
+        let mut lines: Vec<String> = vec![String::new()];
+        for child in node.children().iter() {
+            let nodedata = child.data.borrow();
+            let body = &self.content[nodedata.range.clone()];
+            if nodedata.tag.name != NodeTagName::Text {
+                lines
+                    .last_mut()
+                    .unwrap()
+                    .push_str(&self.generate_inline(child));
+                continue;
+            }
+            for (i, line) in body.split('\n').enumerate() {
+                if i > 0 {
+                    lines.push(String::new());
+                }
+                let current = lines.last_mut().unwrap();
+                let line = if is_quote && current.is_empty() {
+                    let trimmed = line.trim_start();
+                    trimmed.strip_prefix('>').unwrap_or(trimmed)
+                } else {
+                    line
+                };
+                current.push_str(&utils::escape_to_typst(line));
+            }
+        }
+
+        let mut text = String::new();
+        for line in lines.iter().map(|line| line.trim()) {
+            if line.is_empty() {
+                continue;
+            }
+            if let (Some(previous), Some(first)) =
+                (text.chars().last(), line.chars().next())
+            {
+                match self.options.soft_break {
+                    SoftBreak::Space => {
+                        if is_spaced(previous) || is_spaced(first) {
+                            text.push(' ');
+                        }
+                    }
+                    SoftBreak::Newline => text.push('\n'),
+                    SoftBreak::HardBreak => text.push_str(" \\\n"),
+                }
+            }
+            text.push_str(&escape_line_start(line));
+        }
+        text
+    }
+
+    fn generate_inline(&self, node: &Node) -> String {
+        let nodedata = node.data.borrow();
+        let body = &self.content[nodedata.range.clone()];
+        match nodedata.tag.name {
+            NodeTagName::Code => {
+                let code = body.trim_matches('`').trim();
+                if code.contains('`') {
+                    format!("#raw({})", string(code))
+                } else {
+                    format!("`{code}`")
+                }
+            }
+            NodeTagName::Math => {
+                let tex = body.trim_matches('$').trim();
+                let inlined = node.is_inlined(self.content);
+                match (tex_to_typst(tex), inlined) {
+                    (Some(math), true) => format!("${math}$"),
+                    (Some(math), false) => format!("$ {math} $"),
+                    (None, true) => {
+                        format!("#raw(lang: \"latex\", {})", string(tex))
+                    }
+                    (None, false) => format!(
+                        "#raw(block: true, lang: \"latex\", {})",
+                        string(tex)
+                    ),
+                }
+            }
+            NodeTagName::Emphasis(t) => {
+                let body = utils::escape_to_typst(body.trim_matches('*'));
+                match t {
+                    Emphasis::Italics => format!("#emph[{body}]"),
+                    Emphasis::Bold => format!("#strong[{body}]"),
+                }
+            }
+            NodeTagName::Link => {
+                let url = node.get_attr_or("href", "");
+                let name = node.get_attr_or("name", "");
+                if name.is_empty() || name == url {
+                    format!("#link({})", string(&url))
+                } else {
+                    format!(
+                        "#link({})[{}]",
+                        string(&url),
+                        utils::escape_to_typst(&name)
+                    )
+                }
+            }
+            NodeTagName::Image => {
+                let alt = node.get_attr_or("name", "");
+                let src = self.options.image_src(&node.get_attr_or("src", ""));
+                if Path::new(&src).exists() {
+                    format!(
+                        "#figure(image({}), caption: [{}])",
+                        string(&src),
+                        utils::escape_to_typst(&alt)
+                    )
+                } else {
+                    log::warn!("image path [{src}] does not exist, ignored.");
+                    "#strong[could not find image]".to_owned()
+                }
+            }
+            NodeTagName::Extension => {
+                let value = mark::generate(
+                    body,
+                    RenderType::Text,
+                    SanitizePolicy::None,
+                )
+                .unwrap_or_else(|| body.to_owned());
+                utils::escape_to_typst(value.trim())
+            }
+            _ => utils::escape_to_typst(body),
+        }
+    }
+}
+
+/// Escape characters which start a block at the beginning of a line
+fn escape_line_start(line: &str) -> String {
+    let starts_block = match line.chars().next() {
+        Some('=' | '-' | '+' | '/') => true,
+        Some(ch) if ch.is_ascii_digit() => line
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .starts_with('.'),
+        _ => false,
+    };
+    if starts_block {
+        format!("\\{line}")
+    } else {
+        line.to_owned()
+    }
+}
+
+/// A Typst string literal
+fn string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A code fence longer than any backtick run in `code`
+fn fence(code: &str) -> String {
+    let longest = code
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// TeX commands => Typst math symbols or functions
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "alpha"),
+    ("beta", "beta"),
+    ("gamma", "gamma"),
+    ("delta", "delta"),
+    ("epsilon", "epsilon.alt"),
+    ("varepsilon", "epsilon"),
+    ("zeta", "zeta"),
+    ("eta", "eta"),
+    ("theta", "theta"),
+    ("vartheta", "theta.alt"),
+    ("iota", "iota"),
+    ("kappa", "kappa"),
+    ("lambda", "lambda"),
+    ("mu", "mu"),
+    ("nu", "nu"),
+    ("xi", "xi"),
+    ("pi", "pi"),
+    ("rho", "rho"),
+    ("sigma", "sigma"),
+    ("tau", "tau"),
+    ("upsilon", "upsilon"),
+    ("phi", "phi.alt"),
+    ("varphi", "phi"),
+    ("chi", "chi"),
+    ("psi", "psi"),
+    ("omega", "omega"),
+    ("Gamma", "Gamma"),
+    ("Delta", "Delta"),
+    ("Theta", "Theta"),
+    ("Lambda", "Lambda"),
+    ("Xi", "Xi"),
+    ("Pi", "Pi"),
+    ("Sigma", "Sigma"),
+    ("Phi", "Phi"),
+    ("Psi", "Psi"),
+    ("Omega", "Omega"),
+    ("cdot", "dot.op"),
+    ("times", "times"),
+    ("div", "div"),
+    ("pm", "plus.minus"),
+    ("mp", "minus.plus"),
+    ("le", "<="),
+    ("leq", "<="),
+    ("ge", ">="),
+    ("geq", ">="),
+    ("ne", "!="),
+    ("neq", "!="),
+    ("approx", "approx"),
+    ("equiv", "equiv"),
+    ("sim", "tilde.op"),
+    ("propto", "prop"),
+    ("infty", "infinity"),
+    ("partial", "partial"),
+    ("nabla", "nabla"),
+    ("forall", "forall"),
+    ("exists", "exists"),
+    ("in", "in"),
+    ("notin", "in.not"),
+    ("subset", "subset"),
+    ("subseteq", "subset.eq"),
+    ("supset", "supset"),
+    ("cup", "union"),
+    ("cap", "sect"),
+    ("emptyset", "emptyset"),
+    ("to", "->"),
+    ("rightarrow", "->"),
+    ("leftarrow", "<-"),
+    ("Rightarrow", "=>"),
+    ("Leftrightarrow", "<=>"),
+    ("mapsto", "|->"),
+    ("sum", "sum"),
+    ("prod", "product"),
+    ("int", "integral"),
+    ("oint", "integral.cont"),
+    ("lim", "lim"),
+    ("sin", "sin"),
+    ("cos", "cos"),
+    ("tan", "tan"),
+    ("log", "log"),
+    ("ln", "ln"),
+    ("exp", "exp"),
+    ("max", "max"),
+    ("min", "min"),
+    ("ldots", "dots.h"),
+    ("cdots", "dots.c"),
+    ("dots", "dots"),
+    ("quad", "quad"),
+    ("qquad", "wide"),
+    (",", "thin"),
+    (";", "med"),
+    ("{", "\\{"),
+    ("}", "\\}"),
+    ("|", "||"),
+];
+
+/// TeX commands with one argument => Typst functions
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("sqrt", "sqrt"),
+    ("mathbf", "bold"),
+    ("mathrm", "upright"),
+    ("mathit", "italic"),
+    ("mathcal", "cal"),
+    ("mathbb", "bb"),
+    ("hat", "hat"),
+    ("bar", "overline"),
+    ("overline", "overline"),
+    ("vec", "arrow"),
+    ("tilde", "tilde"),
+    ("dot", "dot"),
+];
+
+/// Translate common TeX math into Typst math, `None` is returned if `tex` uses unsupported
+/// commands or syntax
+pub fn tex_to_typst(tex: &str) -> Option<String> {
+    let chars = tex.chars().collect::<Vec<char>>();
+    let mut pos = 0;
+    let math = translate(&chars, &mut pos, None)?;
+    (pos == chars.len()).then_some(math)
+}
+
+/// Translate until `end` (exclusive) or the end of input
+fn translate(
+    chars: &[char],
+    pos: &mut usize,
+    end: Option<char>,
+) -> Option<String> {
+    let mut pieces: Vec<String> = vec![];
+    while *pos < chars.len() {
+        let ch = chars[*pos];
+        if Some(ch) == end {
+            break;
+        }
+        *pos += 1;
+        match ch {
+            ' ' | '\t' | '\n' => {}
+            // braces only group tokens in TeX
+            '{' => {
+                let group = translate(chars, pos, Some('}'))?;
+                expect(chars, pos, '}')?;
+                pieces.push(group);
+            }
+            '}' => return None,
+            '^' | '_' => {
+                let arg = argument(chars, pos)?;
+                let last = pieces.last_mut()?;
+                last.push(ch);
+                last.push_str(&arg);
+            }
+            '\\' => {
+                let name = command(chars, pos)?;
+                match name.as_str() {
+                    "frac" => {
+                        let (a, b) = (group(chars, pos)?, group(chars, pos)?);
+                        pieces.push(format!("frac({a}, {b})"));
+                    }
+                    "sqrt" if chars.get(*pos) == Some(&'[') => {
+                        *pos += 1;
+                        let n = translate(chars, pos, Some(']'))?;
+                        expect(chars, pos, ']')?;
+                        let x = group(chars, pos)?;
+                        pieces.push(format!("root({n}, {x})"));
+                    }
+                    "text" => {
+                        expect(chars, pos, '{')?;
+                        let start = *pos;
+                        while chars.get(*pos).is_some_and(|ch| *ch != '}') {
+                            *pos += 1;
+                        }
+                        let text =
+                            chars[start..*pos].iter().collect::<String>();
+                        expect(chars, pos, '}')?;
+                        pieces.push(string(&text));
+                    }
+                    "left" | "right" => {}
+                    _ => {
+                        if let Some((_, func)) =
+                            FUNCTIONS.iter().find(|(tex, _)| *tex == name)
+                        {
+                            let arg = group(chars, pos)?;
+                            pieces.push(format!("{func}({arg})"));
+                        } else {
+                            let (_, symbol) =
+                                SYMBOLS.iter().find(|(tex, _)| *tex == name)?;
+                            pieces.push(symbol.to_string());
+                        }
+                    }
+                }
+            }
+            ch if ch.is_ascii_digit() || ch == '.' => {
+                let mut number = ch.to_string();
+                while let Some(next) = chars.get(*pos) {
+                    if !next.is_ascii_digit() && *next != '.' {
+                        break;
+                    }
+                    number.push(*next);
+                    *pos += 1;
+                }
+                pieces.push(number);
+            }
+            // TeX letters are separate variables while Typst joins them into one identifier
+            ch if ch.is_alphabetic() => pieces.push(ch.to_string()),
+            '+' | '-' | '=' | '<' | '>' | '(' | ')' | '[' | ']' | '|' | '!'
+            | '\'' | '/' | ':' => pieces.push(ch.to_string()),
+            // commas separate function arguments in Typst
+            ',' if end.is_none() => pieces.push(",".to_owned()),
+            _ => return None,
+        }
+    }
+    Some(pieces.join(" "))
+}
+
+/// Name of a command after `\`
+fn command(chars: &[char], pos: &mut usize) -> Option<String> {
+    let first = *chars.get(*pos)?;
+    *pos += 1;
+    if !first.is_ascii_alphabetic() {
+        return Some(first.to_string());
+    }
+    let mut name = first.to_string();
+    while let Some(ch) = chars.get(*pos).filter(|ch| ch.is_ascii_alphabetic()) {
+        name.push(*ch);
+        *pos += 1;
+    }
+    Some(name)
+}
+
+/// Content of a `{...}` group or a single token
+fn group(chars: &[char], pos: &mut usize) -> Option<String> {
+    while chars.get(*pos) == Some(&' ') {
+        *pos += 1;
+    }
+    if chars.get(*pos) == Some(&'{') {
+        *pos += 1;
+        let group = translate(chars, pos, Some('}'))?;
+        expect(chars, pos, '}')?;
+        Some(group)
+    } else {
+        let single = chars.get(*pos..*pos + 1)?;
+        let mut inner = 0;
+        let token = if single[0] == '\\' {
+            let mut end = *pos + 1;
+            command(chars, &mut end)?;
+            &chars[*pos..end]
+        } else {
+            single
+        };
+        let text = translate(token, &mut inner, None)?;
+        *pos += token.len();
+        Some(text)
+    }
+}
+
+/// Argument of `^` or `_`, groups are parenthesized
+fn argument(chars: &[char], pos: &mut usize) -> Option<String> {
+    let grouped = chars.get(*pos) == Some(&'{');
+    let arg = group(chars, pos)?;
+    if grouped && arg.contains(' ') {
+        Some(format!("({arg})"))
+    } else {
+        Some(arg)
+    }
+}
+
+fn expect(chars: &[char], pos: &mut usize, ch: char) -> Option<()> {
+    if chars.get(*pos) == Some(&ch) {
+        *pos += 1;
+        Some(())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tex_to_typst() {
+        let cases = [
+            (r"a^2 + b^2 = c^2", "a^2 + b^2 = c^2"),
+            (r"\frac{a^{n+1}}{\sqrt{b}}", "frac(a^(n + 1), sqrt(b))"),
+            (r"\sqrt[3]{x} \leq \alpha_i", "root(3, x) <= alpha_i"),
+            (r"\sum_{i=1}^{n} x_i", "sum_(i = 1)^n x_i"),
+            (r"\left( xy \right)", "( x y )"),
+            (r"\mathbf{v} \cdot \vec{u}", "bold(v) dot.op arrow(u)"),
+            (r"f(x) = \text{if } x", "f ( x ) = \"if \" x"),
+            (r"\{a\}", "\\{ a \\}"),
+        ];
+        for (tex, typst) in cases {
+            assert_eq!(tex_to_typst(tex).as_deref(), Some(typst), "{tex}");
+        }
+        assert_eq!(tex_to_typst(r"\begin{matrix}a\end{matrix}"), None);
+        assert_eq!(tex_to_typst(r"a \\ b"), None);
+        assert_eq!(tex_to_typst(r"\frac{a,b}{c}"), None);
+        assert_eq!(tex_to_typst(r"{a"), None);
+    }
+}
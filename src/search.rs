@@ -0,0 +1,354 @@
+//! Full-text search index
+//!
+//! [`SearchIndex`] splits pages into sections by headings and maps tokens to the sections they
+//! appear in, the index is serialized into JSON so it can be loaded by client-side scripts, for
+//! example
+//!
+//!     use concisemark::{search::SearchIndex, Page, PageOptions};
+//!
+//!     let options = PageOptions { heading_ids: true, ..Default::default() };
+//!     let page = Page::new_with("# Install\n\nRun `cargo install`\n\n# 用法\n\n渲染文档", options);
+//!     let mut index = SearchIndex::new();
+//!     index.add(&page, "guide.html");
+//!     let hits = index.search("cargo");
+//!     assert_eq!(hits[0].section.url(), "guide.html#install");
+//!     let hits = index.search("文档");
+//!     assert_eq!(hits[0].section.url(), "guide.html#用法");
+//!     let json = index.to_json();
+//!
+//! The JSON looks like
+//!
+//! ```text
+//! {
+//!   "sections": [
+//!     {"path": "guide.html", "anchor": "install", "title": "Install", "heading": "Install",
+//!      "tags": [], "text": "Run cargo install"},
+//!     ...
+//!   ],
+//!   "terms": {"cargo": [[0, 1]], "install": [[0, 6]], ...}
+//! }
+//! ```
+//!
+//! where each term is mapped to `[section, weight]` pairs, see [`tokenize`] for how text is split
+//! into terms.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    node::{self, NodeTagName},
+    render, utils, Page,
+};
+
+/// Weight of a term found in the page title or tags
+const TITLE_WEIGHT: u32 = 3;
+/// Weight of a term found in the section heading
+const HEADING_WEIGHT: u32 = 2;
+
+/// A part of a page under a heading, the part before the first heading is a section as well
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Section {
+    /// Path or url of the page
+    pub path: String,
+    /// Id of the heading, `None` for the part before the first heading or if the heading has
+    /// no id (see [`PageOptions::heading_ids`](crate::PageOptions::heading_ids))
+    pub anchor: Option<String>,
+    /// Title of the page
+    pub title: String,
+    /// Text of the heading, or the page title if the section has no heading
+    pub heading: String,
+    /// Tags of the page
+    pub tags: Vec<String>,
+    /// Plain text of the section with markups stripped, see
+    /// [`Page::render_text`](crate::Page::render_text)
+    pub text: String,
+}
+
+impl Section {
+    /// Url of the section, that is the page path followed by the anchor
+    pub fn url(&self) -> String {
+        match &self.anchor {
+            Some(anchor) => format!("{}#{anchor}", self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit<'a> {
+    pub section: &'a Section,
+    /// Sum of weights of matched terms
+    pub score: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchIndex {
+    pub sections: Vec<Section>,
+    /// Term => `(section, weight)` pairs ordered by section, weight is the occurrences of the
+    /// term in the section and titles and headings weigh more
+    pub terms: BTreeMap<String, Vec<(usize, u32)>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load an index from its JSON
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Add sections of `page` which lives at `path`
+    ///
+    /// The page title is [`Meta::title`](crate::meta::Meta::title), or the text of the first
+    /// heading, or `path` if there is neither of them.
+    pub fn add(&mut self, page: &Page, path: &str) {
+        let meta = page.meta.as_ref();
+        let options = page.options();
+        let tags = meta.and_then(|meta| meta.tags.clone()).unwrap_or_default();
+        let title = meta
+            .map(|meta| meta.title.clone())
+            .filter(|title| !title.is_empty())
+            .or_else(|| {
+                node::find_nodes_by_tag(&page.ast, NodeTagName::Heading)
+                    .first()
+                    .map(|heading| node::inline_text(heading, &page.content))
+            })
+            .unwrap_or_else(|| path.to_owned());
+
+        let mut section = Section {
+            path: path.to_owned(),
+            anchor: None,
+            title: title.clone(),
+            heading: title.clone(),
+            tags: tags.clone(),
+            text: String::new(),
+        };
+        // the part before the first heading is skipped if it is empty
+        let mut is_heading = false;
+        for child in page.ast.children() {
+            if child.data.borrow().tag.name == NodeTagName::Heading {
+                let heading = Section {
+                    path: path.to_owned(),
+                    anchor: child.data.borrow().tag.attrs.get("id").cloned(),
+                    title: title.clone(),
+                    heading: node::inline_text(&child, &page.content),
+                    tags: tags.clone(),
+                    text: String::new(),
+                };
+                let section = std::mem::replace(&mut section, heading);
+                if is_heading || !section.text.is_empty() {
+                    self.push(section);
+                }
+                is_heading = true;
+            } else {
+                let text =
+                    render::text::generate(&child, &page.content, &options);
+                if !text.is_empty() {
+                    if !section.text.is_empty() {
+                        section.text.push('\n');
+                    }
+                    section.text.push_str(text.trim_end());
+                }
+            }
+        }
+        if is_heading || !section.text.is_empty() {
+            self.push(section);
+        }
+    }
+
+    fn push(&mut self, section: Section) {
+        let id = self.sections.len();
+        let mut weights: BTreeMap<String, u32> = BTreeMap::new();
+        let fields = [
+            (section.title.as_str(), TITLE_WEIGHT),
+            (&section.tags.join(" "), TITLE_WEIGHT),
+            (section.heading.as_str(), HEADING_WEIGHT),
+            (section.text.as_str(), 1),
+        ];
+        for (text, weight) in fields {
+            for term in tokenize(text) {
+                *weights.entry(term).or_default() += weight;
+            }
+        }
+        for (term, weight) in weights {
+            self.terms.entry(term).or_default().push((id, weight));
+        }
+        self.sections.push(section);
+    }
+
+    /// Sections containing all terms of `query`, ordered by score from high to low
+    ///
+    /// A term of the query also matches longer terms starting with it, so incomplete words can be
+    /// searched. A single CJK character also matches bigrams ending with it, since the last
+    /// character of a run of CJK characters only appears at the end of a bigram.
+    pub fn search(&self, query: &str) -> Vec<SearchHit<'_>> {
+        let terms = tokenize(query);
+        if terms.is_empty() {
+            return vec![];
+        }
+        // section => (matched query terms, score)
+        let mut scores: BTreeMap<usize, (usize, u32)> = BTreeMap::new();
+        for (i, term) in terms.iter().enumerate() {
+            let is_cjk_char = term.chars().count() == 1
+                && term.chars().all(|ch| utils::char_width(ch) == 2);
+            let ending = self.terms.iter().filter(|(other, _)| {
+                is_cjk_char
+                    && other.ends_with(term.as_str())
+                    && !other.starts_with(term.as_str())
+            });
+            let matched = self
+                .terms
+                .range(term.clone()..)
+                .take_while(|(other, _)| other.starts_with(term.as_str()))
+                .chain(ending);
+            for (_, postings) in matched {
+                for (section, weight) in postings {
+                    let score = scores.entry(*section).or_default();
+                    // count each query term once for a section
+                    if score.0 == i {
+                        score.0 += 1;
+                    }
+                    if score.0 == i + 1 {
+                        score.1 += weight;
+                    }
+                }
+            }
+        }
+        let mut hits = scores
+            .into_iter()
+            .filter(|(_, (matched, _))| *matched == terms.len())
+            .map(|(section, (_, score))| SearchHit {
+                section: &self.sections[section],
+                score,
+            })
+            .collect::<Vec<_>>();
+        // the sort is stable, so sections with the same score keep their order
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+        hits
+    }
+}
+
+/// Split `text` into lowercased search terms
+///
+/// Letters and digits are split into words by other characters, and east asian characters which
+/// are not separated by spaces are split into overlapping bigrams (a single character is kept as
+/// is).
+///
+///     use concisemark::search::tokenize;
+///
+///     assert_eq!(tokenize("Hello, World!"), ["hello", "world"]);
+///     assert_eq!(tokenize("使用Rust编写"), ["使用", "rust", "编写"]);
+///     assert_eq!(tokenize("全文搜索"), ["全文", "文搜", "搜索"]);
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = vec![];
+    let mut word = String::new();
+    let mut cjk: Vec<char> = vec![];
+    for ch in text.chars().chain(std::iter::once(' ')) {
+        let is_cjk = ch.is_alphanumeric() && utils::char_width(ch) == 2;
+        if !is_cjk && !cjk.is_empty() {
+            if cjk.len() == 1 {
+                terms.push(cjk[0].to_string());
+            } else {
+                terms.extend(
+                    cjk.windows(2).map(|pair| pair.iter().collect::<String>()),
+                );
+            }
+            cjk.clear();
+        }
+        if (is_cjk || !ch.is_alphanumeric()) && !word.is_empty() {
+            terms.push(std::mem::take(&mut word));
+        }
+        if is_cjk {
+            cjk.push(ch);
+        } else if ch.is_alphanumeric() {
+            word.extend(ch.to_lowercase());
+        }
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::PageOptions;
+
+    #[test]
+    fn test_search_index() {
+        let content = indoc! {r#"
+            <!---
+            title = "Guide"
+            date = "2024-01-01 00:00:00"
+            tags = ["intro"]
+            -->
+
+            Read this *first*.
+
+            # Install
+
+            - Run `cargo install concisemark`
+            - Or download binaries
+
+            # 中文搜索
+
+            支持中文分词
+        "#};
+        let options = PageOptions {
+            heading_ids: true,
+            ..Default::default()
+        };
+        let page = Page::new_with(content, options);
+        let mut index = SearchIndex::new();
+        index.add(&page, "guide.html");
+
+        let sections = index
+            .sections
+            .iter()
+            .map(|section| (section.url(), section.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            [
+                ("guide.html".to_owned(), "Read this first."),
+                (
+                    "guide.html#install".to_owned(),
+                    "- Run cargo install concisemark\n- Or download binaries"
+                ),
+                ("guide.html#中文搜索".to_owned(), "支持中文分词"),
+            ]
+        );
+        assert_eq!(index.sections[1].heading, "Install");
+        assert_eq!(index.sections[1].title, "Guide");
+
+        let urls = |query: &str| {
+            index
+                .search(query)
+                .iter()
+                .map(|hit| hit.section.url())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(urls("install"), ["guide.html#install"]);
+        // all terms must be matched
+        assert_eq!(urls("install first"), Vec::<String>::new());
+        assert_eq!(urls("down"), ["guide.html#install"]);
+        assert_eq!(urls("分词"), ["guide.html#中文搜索"]);
+        // the section with the heading ranks first
+        assert_eq!(urls("中文"), ["guide.html#中文搜索"]);
+        assert_eq!(urls("中")[0], "guide.html#中文搜索");
+        // the last character of a run of CJK characters
+        assert_eq!(urls("索"), ["guide.html#中文搜索"]);
+        assert_eq!(urls("词"), ["guide.html#中文搜索"]);
+        // title and tags are in every section
+        assert_eq!(urls("intro").len(), 3);
+
+        let json = index.to_json();
+        assert_eq!(SearchIndex::from_json(&json).unwrap(), index);
+    }
+}
@@ -0,0 +1,773 @@
+//! Static site generation
+//!
+//! [`Site`] renders all documents of a project (see [`Config`]) into HTML files wrapped in a
+//! template, generates an index page for each directory without `index.md` and a listing page for
+//! each tag, and copies other files (images, stylesheets ...) as is.
+//!
+//! A template is an HTML file with the following placeholders
+//!
+//! | placeholder    | value                                                             |
+//! |----------------|-------------------------------------------------------------------|
+//! | `{{title}}`    | [`Meta::title`], the first heading or the file name               |
+//! | `{{subtitle}}` | [`Meta::subtitle`]                                                |
+//! | `{{authors}}`  | [`Meta::authors`] separated by `, `                               |
+//! | `{{date}}`     | [`Meta::date`] in `YYYY-MM-DD` format                             |
+//! | `{{tags}}`     | [`Meta::tags`] as links to tag pages                              |
+//! | `{{body}}`     | the rendered page                                                 |
+//! | `{{toc}}`      | table of contents as nested `ul` lists linking to headings        |
+//! | `{{root}}`     | relative path from the page to the site root such as `../`        |
+//!
+//! All values except `tags`, `body` and `toc` are escaped, missing values are empty.
+//!
+//! Tag pages are written into `tags` (see [`SiteConfig::tags_dir`]) as `<slug>.html` along with an
+//! `index.html` showing a tag cloud, pages can be grouped by tags with [`tag_index`] as well.
+//!
+//! If [`SiteConfig::url`] is given, pages with meta are published as `atom.xml` and `rss.xml`
+//! feeds, see [`Feed`]. If [`SiteConfig::search`] is on, a search index of all pages is written
+//! into `search.json`.
+//!
+//! [`Meta::title`]: crate::meta::Meta::title
+//! [`Meta::subtitle`]: crate::meta::Meta::subtitle
+//! [`Meta::authors`]: crate::meta::Meta::authors
+//! [`Meta::date`]: crate::meta::Meta::date
+//! [`Meta::tags`]: crate::meta::Meta::tags
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    feed::Feed,
+    node::{self, Node, NodeTagName},
+    search::SearchIndex,
+    utils, Page,
+};
+
+/// Site settings in the `[site]` table of the project config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiteConfig {
+    /// Site title used by index pages
+    pub title: Option<String>,
+    /// HTML template file of pages, the bundled template is used if not given
+    pub template: Option<PathBuf>,
+    /// Output directory (relative to the output root) of tag pages
+    pub tags_dir: String,
+    /// Absolute url of the site, `atom.xml` and `rss.xml` feeds of pages with meta are written
+    /// into the output root if it is given
+    pub url: Option<String>,
+    /// Write a search index of all pages into `search.json` in the output root, see
+    /// [`SearchIndex`]
+    pub search: bool,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            template: None,
+            tags_dir: "tags".to_owned(),
+            url: None,
+            search: false,
+        }
+    }
+}
+
+/// A rendered page of the site
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageRef {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub authors: Vec<String>,
+    pub date: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    /// Path of the rendered page relative to the output directory, separated by `/`
+    pub path: String,
+}
+
+impl PageRef {
+    /// A generated listing page such as an index page
+    fn listing(title: &str, path: &str) -> Self {
+        Self {
+            title: title.to_owned(),
+            subtitle: None,
+            authors: vec![],
+            date: None,
+            tags: vec![],
+            path: path.to_owned(),
+        }
+    }
+}
+
+pub struct Site {
+    pub config: Config,
+    template: String,
+}
+
+impl Site {
+    pub const DEFAULT_TEMPLATE: &'static str =
+        include_str!("../assets/site/template.html");
+
+    /// Create a site from project `config`, the template in [`SiteConfig::template`] is loaded
+    pub fn new(config: Config) -> Result<Self> {
+        let template = match &config.site.template {
+            Some(path) => {
+                let path = config.root.join(path);
+                fs::read_to_string(&path).with_context(|| {
+                    format!("failed to read template {}", path.display())
+                })?
+            }
+            None => Self::DEFAULT_TEMPLATE.to_owned(),
+        };
+        Ok(Self { config, template })
+    }
+
+    pub fn with_template<S: Into<String>>(mut self, template: S) -> Self {
+        self.template = template.into();
+        self
+    }
+
+    /// Parse all documents, returns them with their information
+    fn load(&self) -> Result<Vec<(PageRef, Page)>> {
+        let mut pages = vec![];
+        for source in self.config.documents()? {
+            let relpath = source.strip_prefix(&self.config.source)?;
+            let mut options = self.config.options_for(relpath)?;
+            options.heading_ids = true;
+            let content = fs::read_to_string(&source).with_context(|| {
+                format!("failed to read {}", source.display())
+            })?;
+            let page = Page::new_with(content, options);
+            pages.push((page_ref(relpath, &page), page));
+        }
+        Ok(pages)
+    }
+
+    /// Information of all pages of the site
+    pub fn pages(&self) -> Result<Vec<PageRef>> {
+        Ok(self
+            .load()?
+            .into_iter()
+            .map(|(page_ref, _)| page_ref)
+            .collect())
+    }
+
+    /// Build the site into the output directory, returns written files
+    ///
+    /// Heading ids are always generated since the table of contents links to them, and links to
+    /// local markdown files are rewritten to the rendered HTML files.
+    pub fn build(&self) -> Result<Vec<PathBuf>> {
+        let mut written = vec![];
+        let mut pages = vec![];
+        let mut feed = self.config.site.url.as_ref().map(|url| {
+            let title = self.config.site.title.as_deref().unwrap_or("Feed");
+            Feed::new(title, url)
+        });
+        let mut search = self.config.site.search.then(SearchIndex::new);
+        let loaded = self.load()?;
+        let tags = tag_index(
+            &loaded
+                .iter()
+                .map(|(page_ref, _)| page_ref.clone())
+                .collect::<Vec<_>>(),
+        );
+        let slugs = tag_slugs(&tags);
+        for (page_ref, page) in loaded {
+            page.transform(rewrite_markdown_link);
+            let toc = toc(&page);
+            let html = self.fill(&page_ref, &page.render(), &toc, &slugs);
+            written.push(self.write(&page_ref.path, html)?);
+            if let Some(feed) = feed.as_mut() {
+                let link = format!(
+                    "{}/{}",
+                    feed.link.trim_end_matches('/'),
+                    page_ref.path
+                );
+                feed.add(&page, &link);
+            }
+            if let Some(search) = search.as_mut() {
+                search.add(&page, &page_ref.path);
+            }
+            pages.push(page_ref);
+        }
+        if let Some(feed) = feed {
+            written.push(self.write("atom.xml", feed.to_atom())?);
+            written.push(self.write("rss.xml", feed.to_rss())?);
+        }
+        if let Some(search) = search {
+            written.push(self.write("search.json", search.to_json())?);
+        }
+        written.extend(self.build_indexes(&pages, &slugs)?);
+        written.extend(self.build_tag_pages(&tags, &slugs)?);
+        written.extend(self.copy_assets()?);
+        Ok(written)
+    }
+
+    /// Write a listing page for each tag and a tag cloud into [`SiteConfig::tags_dir`]
+    fn build_tag_pages(
+        &self,
+        tags: &BTreeMap<String, Vec<PageRef>>,
+        slugs: &BTreeMap<String, String>,
+    ) -> Result<Vec<PathBuf>> {
+        if tags.is_empty() {
+            return Ok(vec![]);
+        }
+        let dir = self.config.site.tags_dir.trim_matches('/');
+        let mut written = vec![];
+        for (tag, pages) in tags.iter() {
+            let path = join(dir, &format!("{}.html", slugs[tag]));
+            let tag_ref = PageRef::listing(tag, &path);
+            let body = index_list(dir, &pages.iter().collect::<Vec<_>>());
+            let html = self.fill(&tag_ref, &body, "", slugs);
+            written.push(self.write(&path, html)?);
+        }
+        let path = join(dir, "index.html");
+        let html = self.fill(
+            &PageRef::listing("Tags", &path),
+            &tag_cloud(tags, slugs),
+            "",
+            slugs,
+        );
+        written.push(self.write(&path, html)?);
+        Ok(written)
+    }
+
+    /// Write an index page for each directory containing pages but no `index.md`
+    fn build_indexes(
+        &self,
+        pages: &[PageRef],
+        slugs: &BTreeMap<String, String>,
+    ) -> Result<Vec<PathBuf>> {
+        // directory => pages in it and its sub directories
+        let mut dirs: BTreeMap<String, Vec<&PageRef>> = BTreeMap::new();
+        for page in pages {
+            let mut dir = page.path.as_str();
+            while let Some((parent, _)) = dir.rsplit_once('/') {
+                dirs.entry(parent.to_owned()).or_default().push(page);
+                dir = parent;
+            }
+            dirs.entry("".to_owned()).or_default().push(page);
+        }
+
+        let mut written = vec![];
+        for (dir, mut pages) in dirs {
+            let index = join(&dir, "index.html");
+            if pages.iter().any(|page| page.path == index) {
+                continue;
+            }
+            sort_pages(&mut pages);
+            let title = match dir.rsplit('/').next() {
+                Some(name) if !name.is_empty() => name.to_owned(),
+                _ => self
+                    .config
+                    .site
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| "Index".to_owned()),
+            };
+            let index_ref = PageRef::listing(&title, &index);
+            let body = index_list(&dir, &pages);
+            let html = self.fill(&index_ref, &body, "", slugs);
+            written.push(self.write(&index, html)?);
+        }
+        Ok(written)
+    }
+
+    /// Copy files which are not markdown files into the output directory
+    fn copy_assets(&self) -> Result<Vec<PathBuf>> {
+        let mut copied = vec![];
+        let mut dirs = vec![self.config.source.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let relpath = path.strip_prefix(&self.config.source)?;
+                let skipped = path.starts_with(&self.config.output)
+                    || self.config.is_ignored(relpath)
+                    || path.file_name().is_some_and(|name| {
+                        name == Config::FILE_NAME
+                            || name.to_string_lossy().starts_with('.')
+                    })
+                    || self.config.site.template.as_ref().is_some_and(
+                        |template| path == self.config.root.join(template),
+                    );
+                if skipped {
+                    continue;
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if !path.extension().is_some_and(|ext| ext == "md") {
+                    let target = self.config.output.join(relpath);
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(&path, &target).with_context(|| {
+                        format!("failed to copy {}", path.display())
+                    })?;
+                    copied.push(target);
+                }
+            }
+        }
+        copied.sort();
+        Ok(copied)
+    }
+
+    /// Fill the template with page information, tags link to their pages named by `slugs`
+    fn fill(
+        &self,
+        page: &PageRef,
+        body: &str,
+        toc: &str,
+        slugs: &BTreeMap<String, String>,
+    ) -> String {
+        let depth = page.path.matches('/').count();
+        let tags_dir = self.config.site.tags_dir.trim_matches('/');
+        let tags = page
+            .tags
+            .iter()
+            .filter_map(|tag| slugs.get(tag.trim()).map(|slug| (tag, slug)))
+            .map(|(tag, slug)| {
+                let path = join(tags_dir, &format!("{slug}.html"));
+                format!(
+                    r#"<a class="tag" href="{}{}">{}</a>"#,
+                    "../".repeat(depth),
+                    utils::escape_html_double_quote(&path),
+                    utils::escape_to_html(tag)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        let escaped = |text: &str| utils::escape_to_html(text);
+        let date = page
+            .date
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let vars = [
+            ("title", escaped(&page.title)),
+            ("subtitle", escaped(page.subtitle.as_deref().unwrap_or(""))),
+            ("authors", escaped(&page.authors.join(", "))),
+            ("date", date),
+            ("tags", tags),
+            ("root", "../".repeat(depth)),
+            ("toc", toc.to_owned()),
+            ("body", body.to_owned()),
+        ];
+        fill_template(&self.template, &vars)
+    }
+
+    fn write(&self, path: &str, content: String) -> Result<PathBuf> {
+        let target = self.config.output.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, content)
+            .with_context(|| format!("failed to write {}", target.display()))?;
+        Ok(target)
+    }
+}
+
+/// Replace `{{name}}` placeholders in `template` in one pass, so placeholders in values are kept
+fn fill_template(template: &str, vars: &[(&str, String)]) -> String {
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find("}}").and_then(|end| {
+            let name = rest[2..end].trim();
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (value, end + 2))
+        });
+        match value {
+            Some((value, size)) => {
+                text.push_str(value);
+                rest = &rest[size..];
+            }
+            None => {
+                text.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Collect information of `page` whose source is `relpath` relative to the source directory
+fn page_ref(relpath: &Path, page: &Page) -> PageRef {
+    let path = relpath
+        .with_extension("html")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let heading = node::find_nodes_by_tag(&page.ast, NodeTagName::Heading)
+        .first()
+        .map(|heading| node::inline_text(heading, &page.content))
+        .filter(|text| !text.is_empty());
+    let stem = relpath
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match &page.meta {
+        Some(meta) => PageRef {
+            title: if meta.title.is_empty() {
+                heading.unwrap_or(stem)
+            } else {
+                meta.title.clone()
+            },
+            subtitle: meta.subtitle.clone(),
+            authors: meta.authors.clone().unwrap_or_default(),
+            date: Some(meta.date),
+            tags: meta.tags.clone().unwrap_or_default(),
+            path,
+        },
+        None => PageRef {
+            title: heading.unwrap_or(stem),
+            subtitle: None,
+            authors: vec![],
+            date: None,
+            tags: vec![],
+            path,
+        },
+    }
+}
+
+/// Group `pages` by their tags, pages of a tag are sorted by date from newest to oldest
+///
+///     use concisemark::site::{tag_index, PageRef};
+///
+///     let page = |path: &str, tags: &[&str]| PageRef {
+///         title: path.to_owned(),
+///         subtitle: None,
+///         authors: vec![],
+///         date: None,
+///         tags: tags.iter().map(|tag| tag.to_string()).collect(),
+///         path: path.to_owned(),
+///     };
+///     let pages = [page("a.html", &["rust", "cli"]), page("b.html", &["rust"])];
+///     let tags = tag_index(&pages);
+///     assert_eq!(tags.keys().collect::<Vec<_>>(), ["cli", "rust"]);
+///     assert_eq!(tags["rust"].len(), 2);
+pub fn tag_index(pages: &[PageRef]) -> BTreeMap<String, Vec<PageRef>> {
+    let mut tags: BTreeMap<String, Vec<&PageRef>> = BTreeMap::new();
+    for page in pages {
+        for tag in page.tags.iter() {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                continue;
+            }
+            let pages = tags.entry(tag.to_owned()).or_default();
+            if !pages.iter().any(|other| other.path == page.path) {
+                pages.push(page);
+            }
+        }
+    }
+    tags.into_iter()
+        .map(|(tag, mut pages)| {
+            sort_pages(&mut pages);
+            (tag, pages.into_iter().cloned().collect())
+        })
+        .collect()
+}
+
+/// File names (without `.html`) of tag pages, tags are slugified and a slug used by another tag
+/// is suffixed with `-1`, `-2` ... as heading ids
+///
+///     use concisemark::site::{tag_index, tag_slugs, PageRef};
+///
+///     let page = PageRef {
+///         title: "a".to_owned(),
+///         subtitle: None,
+///         authors: vec![],
+///         date: None,
+///         tags: vec!["Web Dev".to_owned(), "web-dev".to_owned()],
+///         path: "a.html".to_owned(),
+///     };
+///     let slugs = tag_slugs(&tag_index(&[page]));
+///     assert_eq!(slugs["Web Dev"], "web-dev");
+///     assert_eq!(slugs["web-dev"], "web-dev-1");
+pub fn tag_slugs(
+    tags: &BTreeMap<String, Vec<PageRef>>,
+) -> BTreeMap<String, String> {
+    // `index.html` is the tag cloud
+    let mut used = vec!["index".to_owned()];
+    let mut slugs = BTreeMap::new();
+    for tag in tags.keys() {
+        let slug = utils::slugify(tag);
+        let mut unique = slug.clone();
+        let mut count = 0;
+        while used.contains(&unique) {
+            count += 1;
+            unique = format!("{slug}-{count}");
+        }
+        used.push(unique.clone());
+        slugs.insert(tag.clone(), unique);
+    }
+    slugs
+}
+
+/// A tag cloud linking to tag pages named by `slugs` (see [`tag_slugs`]), font size of a tag
+/// grows with its page count
+pub fn tag_cloud(
+    tags: &BTreeMap<String, Vec<PageRef>>,
+    slugs: &BTreeMap<String, String>,
+) -> String {
+    let counts = tags.values().map(|pages| pages.len());
+    let (min, max) =
+        (counts.clone().min().unwrap_or(0), counts.max().unwrap_or(0));
+    let mut html = r#"<ul class="tag-cloud">"#.to_owned();
+    for (tag, pages) in tags {
+        let scale = if max > min {
+            (pages.len() - min) as f64 / (max - min) as f64
+        } else {
+            0.0
+        };
+        html.push_str(&format!(
+            r#"<li><a href="{}.html" style="font-size: {:.2}em">{}</a> <span>({})</span></li>"#,
+            utils::escape_html_double_quote(&slugs[tag]),
+            1.0 + scale,
+            utils::escape_to_html(tag),
+            pages.len()
+        ));
+    }
+    html.push_str("</ul>");
+    html
+}
+
+/// Sort pages by date from newest to oldest, pages without date are put last
+pub(crate) fn sort_pages(pages: &mut [&PageRef]) {
+    pages.sort_by(|a, b| {
+        b.date
+            .cmp(&a.date)
+            .then_with(|| a.title.cmp(&b.title))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+}
+
+/// `ul` list linking to `pages` from directory `dir`
+pub(crate) fn index_list(dir: &str, pages: &[&PageRef]) -> String {
+    let mut html = r#"<ul class="index">"#.to_owned();
+    for page in pages {
+        let href = relative_path(dir, &page.path);
+        let date = page
+            .date
+            .map(|date| format!(" <time>{}</time>", date.format("%Y-%m-%d")))
+            .unwrap_or_default();
+        html.push_str(&format!(
+            r#"<li><a href="{}">{}</a>{date}</li>"#,
+            utils::escape_html_double_quote(&href),
+            utils::escape_to_html(&page.title),
+        ));
+    }
+    html.push_str("</ul>");
+    html
+}
+
+/// Path of `path` relative to directory `dir`, both are relative to the site root
+pub(crate) fn relative_path(dir: &str, path: &str) -> String {
+    match path.strip_prefix(dir) {
+        Some(rest) if dir.is_empty() => rest.to_owned(),
+        Some(rest) if rest.starts_with('/') => rest[1..].to_owned(),
+        _ => {
+            let depth = dir.split('/').filter(|name| !name.is_empty()).count();
+            format!("{}{path}", "../".repeat(depth))
+        }
+    }
+}
+
+pub(crate) fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{dir}/{name}")
+    }
+}
+
+/// Table of contents of `page` as nested `ul` lists
+pub fn toc(page: &Page) -> String {
+    let headings = node::find_nodes_by_tag(&page.ast, NodeTagName::Heading);
+    let mut html = String::new();
+    // levels of opened lists
+    let mut levels: Vec<usize> = vec![];
+    for heading in headings.iter() {
+        let level = heading
+            .get_attr_or("level", "1")
+            .parse::<usize>()
+            .unwrap_or(1);
+        while levels.last().is_some_and(|&last| last > level) {
+            levels.pop();
+            html.push_str("</li></ul>");
+        }
+        if levels.last() == Some(&level) {
+            html.push_str("</li>");
+        } else {
+            levels.push(level);
+            html.push_str(if levels.len() == 1 {
+                r#"<ul class="toc">"#
+            } else {
+                "<ul>"
+            });
+        }
+        let text =
+            utils::escape_to_html(&node::inline_text(heading, &page.content));
+        match heading.data.borrow().tag.attrs.get("id") {
+            Some(id) => html.push_str(&format!(
+                r##"<li><a href="#{}">{text}</a>"##,
+                utils::escape_html_double_quote(id)
+            )),
+            None => html.push_str(&format!("<li>{text}")),
+        }
+    }
+    for _ in levels {
+        html.push_str("</li></ul>");
+    }
+    html
+}
+
+/// Rewrite links to local markdown files into links to the rendered HTML files
+fn rewrite_markdown_link(node: &Node) -> Result<(), ()> {
+    let mut nodedata = node.data.borrow_mut();
+    if nodedata.tag.name != NodeTagName::Link {
+        return Ok(());
+    }
+    if let Some(href) = nodedata.tag.attrs.get_mut("href") {
+        let (path, fragment) = match href.find('#') {
+            Some(pos) => href.split_at(pos),
+            None => (href.as_str(), ""),
+        };
+        if !path.contains("://") && path.ends_with(".md") {
+            *href = format!("{}.html{fragment}", &path[..path.len() - 3]);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_template() {
+        let vars =
+            [("title", "a {{body}}".to_owned()), ("body", "b".to_owned())];
+        assert_eq!(
+            fill_template("{{title}}|{{ body }}|{{unknown}}|{{", &vars),
+            "a {{body}}|b|{{unknown}}|{{"
+        );
+    }
+
+    #[test]
+    fn test_toc() {
+        let options = crate::PageOptions {
+            heading_ids: true,
+            ..Default::default()
+        };
+        let page =
+            Page::new_with("# A\n\n## B\n\n### C\n\n## D\n\n# E\n", options);
+        assert_eq!(
+            toc(&page),
+            concat!(
+                r##"<ul class="toc"><li><a href="#a">A</a><ul><li><a href="#b">B</a>"##,
+                r##"<ul><li><a href="#c">C</a></li></ul></li><li><a href="#d">D</a></li></ul>"##,
+                r##"</li><li><a href="#e">E</a></li></ul>"##,
+            )
+        );
+    }
+
+    #[test]
+    fn test_site() {
+        let root = std::env::temp_dir()
+            .join(format!("concisemark-site-{}", std::process::id()));
+        let source = root.join("docs");
+        fs::create_dir_all(source.join("guide/images")).unwrap();
+        fs::write(
+            source.join("guide/old.md"),
+            "<!---\ntitle = \"Old <post>\"\ndate = \"2023-01-01 00:00:00\"\ntags = [\"rust\", \"Web Dev\"]\n-->\n# Old\n",
+        )
+        .unwrap();
+        fs::write(
+            source.join("guide/new.md"),
+            "<!---\ntitle = \"New\"\ndate = \"2024-01-01 00:00:00\"\ntags = [\"rust\", \"web-dev\"]\n-->\nSee [old](old.md#old)\n",
+        )
+        .unwrap();
+        fs::write(source.join("guide/images/logo.png"), "png").unwrap();
+        fs::write(source.join("index.md"), "# Home\n").unwrap();
+
+        let config =
+            Config::parse("source = \"docs\"\noutput = \"out\"\n[site]\nurl = \"https://example.com\"\nsearch = true", &root)
+                .unwrap();
+        let site = Site::new(config).unwrap().with_template(
+            "<title>{{title}}</title>{{date}}|{{root}}|{{body}}|{{tags}}",
+        );
+        let written = site.build().unwrap();
+        let output = root.join("out");
+        assert_eq!(written.len(), 12);
+
+        let old = fs::read_to_string(output.join("guide/old.html")).unwrap();
+        assert!(
+            old.starts_with("<title>Old &lt;post&gt;</title>2023-01-01|../|")
+        );
+        let new = fs::read_to_string(output.join("guide/new.html")).unwrap();
+        assert!(new.contains(r#"href="old.html#old""#));
+        let index =
+            fs::read_to_string(output.join("guide/index.html")).unwrap();
+        let (new_pos, old_pos) = (
+            index.find("new.html").unwrap(),
+            index.find("old.html").unwrap(),
+        );
+        assert!(new_pos < old_pos);
+        // the root index is written from index.md
+        let home = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(home.starts_with("<title>Home</title>|"));
+        assert!(output.join("guide/images/logo.png").exists());
+        let atom = fs::read_to_string(output.join("atom.xml")).unwrap();
+        assert!(atom.contains("<id>https://example.com/guide/new.html</id>"));
+        assert!(!atom.contains("index.html"));
+        let search = SearchIndex::from_json(
+            &fs::read_to_string(output.join("search.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(search.search("home")[0].section.url(), "index.html#home");
+
+        assert!(old.ends_with(
+            r#"|<a class="tag" href="../tags/rust.html">rust</a> <a class="tag" href="../tags/web-dev.html">Web Dev</a>"#
+        ));
+        let rust = fs::read_to_string(output.join("tags/rust.html")).unwrap();
+        assert!(rust.starts_with("<title>rust</title>|../|"));
+        assert!(
+            rust.find("../guide/new.html").unwrap()
+                < rust.find("../guide/old.html").unwrap()
+        );
+        let cloud = fs::read_to_string(output.join("tags/index.html")).unwrap();
+        assert!(cloud.contains(
+            r#"<a href="rust.html" style="font-size: 2.00em">rust</a> <span>(2)</span>"#
+        ));
+        assert!(cloud.contains(
+            r#"<a href="web-dev.html" style="font-size: 1.00em">Web Dev</a> <span>(1)</span>"#
+        ));
+        // `web-dev` slugifies to the slug of `Web Dev` as well
+        assert!(cloud.contains(
+            r#"<a href="web-dev-1.html" style="font-size: 1.00em">web-dev</a> <span>(1)</span>"#
+        ));
+        assert!(new.contains(
+            r#"<a class="tag" href="../tags/web-dev-1.html">web-dev</a>"#
+        ));
+        let web_dev =
+            fs::read_to_string(output.join("tags/web-dev.html")).unwrap();
+        assert!(web_dev.starts_with("<title>Web Dev</title>"));
+        assert!(web_dev.contains("old.html") && !web_dev.contains("new.html"));
+        let web_dev =
+            fs::read_to_string(output.join("tags/web-dev-1.html")).unwrap();
+        assert!(web_dev.starts_with("<title>web-dev</title>"));
+        assert!(web_dev.contains("new.html") && !web_dev.contains("old.html"));
+
+        _ = fs::remove_dir_all(root);
+    }
+}
@@ -3,6 +3,7 @@ use std::ops::Range;
 use anyhow::Result;
 
 use super::Property;
+use crate::diagnostic::Diagnostic;
 
 #[derive(Debug)]
 pub struct List {
@@ -13,6 +14,7 @@ pub struct List {
 pub struct ListIterator<'a> {
     list: &'a List,
     pos: usize,
+    diagnostic: Option<Diagnostic>,
 }
 
 #[derive(Debug)]
@@ -44,8 +46,20 @@ impl List {
         })
     }
 
-    pub fn iter(&self) -> ListIterator {
-        ListIterator { list: self, pos: 0 }
+    pub fn iter(&self) -> ListIterator<'_> {
+        ListIterator {
+            list: self,
+            pos: 0,
+            diagnostic: None,
+        }
+    }
+}
+
+impl<'a> ListIterator<'a> {
+    /// Take the diagnostic explaining why the iteration stops before the end of the list, its
+    /// range is relative to the list content
+    pub fn take_diagnostic(&mut self) -> Option<Diagnostic> {
+        self.diagnostic.take()
     }
 }
 
@@ -62,16 +76,30 @@ impl<'a> Iterator for ListIterator<'a> {
             remained_content.split_inclusive("\n").nth(0)
         {
             let trimed_head_line = headline.trim_start();
+            let range = self.pos..(self.pos + headline.trim_end().len());
             if !trimed_head_line.starts_with(List::LIST_MARK) {
-                log::warn!(
-                    "list does not start with list mark: [{}]",
-                    headline
-                );
+                self.diagnostic = Some(Diagnostic::error(
+                    "malformed-list",
+                    format!(
+                        "list item does not start with `{}`, the rest of the list is dropped",
+                        List::LIST_MARK.trim()
+                    ),
+                    range,
+                ));
+                self.pos = content.len();
                 return None;
             }
             let indent = headline.len() - trimed_head_line.len();
             if indent % List::INDENT_MARK.len() != 0 {
-                log::warn!("incorrect list indent: {}", headline);
+                self.diagnostic = Some(Diagnostic::error(
+                    "list-indent",
+                    format!(
+                        "list item is indented by {indent} spaces which is not a multiple of {}, the rest of the list is dropped",
+                        List::INDENT_MARK.len()
+                    ),
+                    range,
+                ));
+                self.pos = content.len();
                 return None;
             }
             indent
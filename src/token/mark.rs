@@ -6,9 +6,27 @@ pub struct Mark {
     pub size: usize,
 }
 
+/// Closing parts of mark bodies found missing when scanning a text from left to right
+///
+/// Once the body of a mark such as `@math{x` is found unclosed, there is no `}` in the rest of the
+/// text, so later marks closed by `}` or `}}` are known to be unclosed without scanning the rest
+/// of the text again for every `@`.
+#[derive(Debug, Default)]
+pub struct MarkScan {
+    missing: Vec<String>,
+}
+
+/// Why a text is not a mark
+enum MarkError {
+    Invalid,
+    /// The mark body is not closed, the byte size of the opening part is given
+    Unclosed(usize),
+}
+
 impl Mark {
     const MARK_TAG_LIST: &'static [&'static str] = &[
         "math", "sym", "plot", "img", "video", "emoji", "a", "char", "kbd",
+        "note",
     ];
 
     pub fn new_from_str<S: AsRef<str>>(content: S) -> Option<Mark> {
@@ -18,8 +36,27 @@ impl Mark {
 
     // format: @<name>[attrs]{value}
     pub fn new(chars: &[char]) -> Option<Mark> {
+        Self::parse(chars, &mut MarkScan::default()).ok()
+    }
+
+    /// Parse the mark at the start of `chars`, which is the rest of a text scanned by `scan` from
+    /// left to right
+    ///
+    /// If `chars` starts with a mark whose body is never closed such as `@math{x`, the byte size
+    /// of its opening part `@math{` is given as the error.
+    pub fn scan(
+        chars: &[char],
+        scan: &mut MarkScan,
+    ) -> Result<Mark, Option<usize>> {
+        Self::parse(chars, scan).map_err(|e| match e {
+            MarkError::Unclosed(size) => Some(size),
+            MarkError::Invalid => None,
+        })
+    }
+
+    fn parse(chars: &[char], scan: &mut MarkScan) -> Result<Mark, MarkError> {
         if chars.is_empty() || chars[0] != '@' {
-            return None;
+            return Err(MarkError::Invalid);
         }
 
         let mut has_syntax_error = true;
@@ -42,13 +79,13 @@ impl Mark {
             .copied()
             .collect();
         if has_syntax_error {
-            return None;
+            return Err(MarkError::Invalid);
         }
 
         let attrbeg = head.iter().position(|&c| c == '[');
         let attrend = head.iter().position(|&c| c == ']');
         let (tag, attrs) = match (attrbeg, attrend) {
-            (Some(beg), Some(end)) => {
+            (Some(beg), Some(end)) if beg < end => {
                 let tag = head[1..beg].iter().collect::<String>();
                 let attrs = head[beg + 1..end].iter().collect::<String>();
                 (tag, attrs)
@@ -57,11 +94,11 @@ impl Mark {
                 (head[1..].iter().collect::<String>(), "".to_string())
             }
             _ => {
-                return None;
+                return Err(MarkError::Invalid);
             }
         };
         if !Self::MARK_TAG_LIST.contains(&tag.as_str()) {
-            return None;
+            return Err(MarkError::Invalid);
         }
 
         let (start_mark_char, end_mark_char) =
@@ -70,10 +107,10 @@ impl Mark {
                     '\x7b' => ('{', '}'),
                     '\x28' => ('(', ')'),
                     '\x3c' => ('<', '>'),
-                    _ => return None,
+                    _ => return Err(MarkError::Invalid),
                 }
             } else {
-                return None;
+                return Err(MarkError::Invalid);
             };
 
         let end_mark: String = chars[head.len()..]
@@ -83,7 +120,16 @@ impl Mark {
             .collect();
         // no start mark
         if end_mark.is_empty() {
-            return None;
+            return Err(MarkError::Invalid);
+        }
+
+        let opening = head.iter().collect::<String>().len() + end_mark.len();
+        if scan
+            .missing
+            .iter()
+            .any(|missing| end_mark.starts_with(missing.as_str()))
+        {
+            return Err(MarkError::Unclosed(opening));
         }
 
         has_syntax_error = true;
@@ -102,11 +148,12 @@ impl Mark {
             .map(|chunk| chunk[0])
             .collect();
         if has_syntax_error {
-            return None;
+            scan.missing.push(end_mark);
+            return Err(MarkError::Unclosed(opening));
         }
         let body = body.iter().collect::<String>();
 
-        Some(Mark {
+        Ok(Mark {
             name: tag.trim().to_string(),
             attrs: attrs.trim().to_string(),
             value: body.trim().to_string(),
@@ -117,3 +164,28 @@ impl Mark {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let chars = "@math{a @math{{b @kbd(c) @a{d".chars().collect::<Vec<_>>();
+        let mut scan = MarkScan::default();
+        assert_eq!(Mark::scan(&chars, &mut scan).err(), Some(Some(6)));
+        // known to be unclosed since there is no `}` after the first mark
+        assert_eq!(Mark::scan(&chars[8..], &mut scan).err(), Some(Some(7)));
+        let mark = Mark::scan(&chars[17..], &mut scan).unwrap();
+        assert_eq!((mark.name.as_str(), mark.value.as_str()), ("kbd", "c"));
+        assert_eq!(Mark::scan(&chars[25..], &mut scan).err(), Some(Some(3)));
+        assert_eq!(Mark::scan(&chars[1..], &mut scan).err(), Some(None));
+    }
+
+    #[test]
+    fn test_attrs_out_of_order() {
+        // `]` before `[` used to slice the head with a reversed range and panic
+        assert!(Mark::new_from_str("@kbd]x[{y}").is_none());
+        assert!(Mark::new_from_str("@kbd][{y}").is_none());
+    }
+}
@@ -12,8 +12,8 @@ use anyhow::{ensure, Result};
 pub use codeblock::Codeblock;
 pub use heading::Heading;
 pub use link::Link;
-pub use list::List;
-pub use mark::Mark;
+pub use list::{List, ListItem};
+pub use mark::{Mark, MarkScan};
 pub use pair::Pair;
 pub use paragraph::Paragraph;
 pub use property::Property;
@@ -64,6 +64,10 @@ impl Token {
             Self::BlankLine(sz) => *sz,
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub struct Tokenizer<'a> {
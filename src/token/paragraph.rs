@@ -1,6 +1,6 @@
 use anyhow::Result;
 
-use super::{Pair, Property};
+use super::{Mark, MarkScan, Pair, Property};
 
 #[derive(Debug)]
 pub struct Paragraph {
@@ -17,8 +17,9 @@ impl Paragraph {
         let chars = text.chars().collect::<Vec<char>>();
         let mut para = String::new();
         let mut pos = 0;
+        let mut marks = MarkScan::default();
         while pos < chars.len() {
-            if let Some(mark) = super::Mark::new(&chars[pos..]) {
+            if let Ok(mark) = Mark::scan(&chars[pos..], &mut marks) {
                 let t = chars[pos..]
                     .iter()
                     .map(|c| c.to_string())
@@ -4,16 +4,20 @@ use std::{
     path::{Path, PathBuf},
 };
 
+/// Escape `text` for a double-quoted HTML attribute value
 pub fn escape_html_double_quote(text: &str) -> String {
-    text.chars()
-        .map(|x| {
-            if x == '"' {
-                "&quot;".to_string()
-            } else {
-                x.to_string()
-            }
-        })
-        .collect::<String>()
+    let mut html = String::new();
+    for ch in text.chars() {
+        match ch {
+            '&' => html.push_str("&amp;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            _ => html.push(ch),
+        }
+    }
+    html
 }
 
 pub fn escape_to_html(text: &str) -> String {
@@ -37,6 +41,66 @@ pub fn escape_to_html(text: &str) -> String {
     html
 }
 
+/// Escape `text` for XML text and attribute values, characters not allowed in XML 1.0 (control
+/// characters except tab and newlines) are dropped
+pub fn escape_to_xml(text: &str) -> String {
+    let mut xml = String::new();
+    for ch in text.chars() {
+        match ch {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' => xml.push_str("&quot;"),
+            '\'' => xml.push_str("&apos;"),
+            _ if !is_xml_char(ch) => {}
+            _ => xml.push(ch),
+        }
+    }
+    xml
+}
+
+/// Whether `ch` is allowed in XML 1.0 documents, control characters except tab and newlines are
+/// not
+pub fn is_xml_char(ch: char) -> bool {
+    matches!(ch, '\t' | '\n' | '\r')
+        || (ch >= ' ' && ch != '\u{FFFE}' && ch != '\u{FFFF}')
+}
+
+/// Escape `text` for Typst markup, see also [Typst escapes](https://typst.app/docs/reference/syntax/#escapes)
+pub fn escape_to_typst(text: &str) -> String {
+    let mut typst = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' | '#' | '*' | '_' | '`' | '$' | '<' | '>' | '@' | '['
+            | ']' | '~' => {
+                typst.push('\\');
+                typst.push(ch);
+            }
+            // `//` and `/*` start comments
+            '/' if matches!(chars.peek(), Some('/' | '*')) => {
+                typst.push_str("\\/");
+            }
+            _ => typst.push(ch),
+        }
+    }
+    typst
+}
+
+/// Escape `text` for roff, backslashes are printed as is and hyphens are minus signs so options
+/// such as `--help` can be copied, note that `.` and `'` at the start of a line are not escaped
+pub fn escape_to_roff(text: &str) -> String {
+    let mut roff = String::new();
+    for ch in text.chars() {
+        match ch {
+            '\\' => roff.push_str("\\e"),
+            '-' => roff.push_str("\\-"),
+            _ => roff.push(ch),
+        }
+    }
+    roff
+}
+
 pub fn escape_to_tex(text: &str) -> String {
     let mut content = String::new();
     for ch in text.chars() {
@@ -153,3 +217,86 @@ pub fn remove_indent<S: AsRef<str>>(content: S) -> String {
         .collect::<Vec<&str>>();
     content.join("").to_string()
 }
+
+/// Get the display width of a character in monospace fonts, east asian wide and fullwidth
+/// characters take two columns
+pub fn char_width(ch: char) -> usize {
+    match ch as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x2FFFD
+        | 0x30000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Get the display width of text in monospace fonts, see [`char_width`]
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Convert `text` into a slug which can be used as an html id or a file name
+///
+/// Letters and digits (including east asian characters) are kept and lowercased, whitespace and
+/// `-` are converted into a single `-`, other characters are dropped.
+///
+///     use concisemark::utils::slugify;
+///
+///     assert_eq!(slugify("Hello, World!"), "hello-world");
+///     assert_eq!(slugify("ConciseMark 简介"), "concisemark-简介");
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for ch in text.chars() {
+        if ch.is_alphanumeric() || ch == '_' {
+            slug.extend(ch.to_lowercase());
+        } else if (ch.is_whitespace() || ch == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        return "section".to_owned();
+    }
+    slug.to_owned()
+}
+
+/// The lowercased scheme of `url` as browsers parse it, `None` for relative urls
+///
+/// Leading control characters and spaces are skipped, and tabs and line breaks are removed as
+/// browsers do, so `" Java\tScript:alert(1)"` is a `javascript` url.
+///
+///     use concisemark::utils::url_scheme;
+///
+///     assert_eq!(url_scheme("HTTPS://example.com"), Some("https".to_owned()));
+///     assert_eq!(url_scheme(" java\tscript:alert(1)"), Some("javascript".to_owned()));
+///     assert_eq!(url_scheme("docs/intro.html#a:b"), None);
+///     assert_eq!(url_scheme("//example.com"), None);
+pub fn url_scheme(url: &str) -> Option<String> {
+    let url = url
+        .trim_start_matches(|c: char| c <= ' ')
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'));
+    let mut scheme = String::new();
+    for ch in url {
+        match ch {
+            ':' if !scheme.is_empty() => return Some(scheme),
+            'a'..='z' | 'A'..='Z' => scheme.push(ch.to_ascii_lowercase()),
+            '0'..='9' | '+' | '-' | '.' if !scheme.is_empty() => {
+                scheme.push(ch)
+            }
+            _ => return None,
+        }
+    }
+    None
+}